@@ -49,6 +49,8 @@ typedef struct {{
             "pthread_mutex_t",
             "pthread_mutexattr_t",
             "epoll_event",
+            "pollfd",
+            "nfds_t",
             "iovec",
             "clockid_t",
             "rlimit",
@@ -65,6 +67,7 @@ typedef struct {{
             "_SC_.*",
             "EPOLL_CTL_.*",
             "EPOLL.*",
+            "POLL.*",
             "RLIMIT_.*",
             "EAI_.*",
             "MAXADDRS",
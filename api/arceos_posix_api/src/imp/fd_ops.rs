@@ -1,4 +1,4 @@
-use alloc::sync::Arc;
+use alloc::{string::String, sync::Arc};
 use core::ffi::c_int;
 
 use axerrno::{LinuxError, LinuxResult};
@@ -20,10 +20,43 @@ pub trait FileLike: Send + Sync {
     fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync>;
     fn poll(&self) -> LinuxResult<PollState>;
     fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult;
+    /// The path this file was opened with, or a synthetic name such as
+    /// `pipe:[...]` for files that do not live in the filesystem.
+    fn path(&self) -> String;
 }
 
 def_resource! {
     pub(crate) static FD_TABLE: ResArc<RwLock<FlattenObjects<Arc<dyn FileLike>, AX_FILE_LIMIT>>> = ResArc::new();
+    /// Soft and hard limits of open files (`RLIMIT_NOFILE`): new fds must be
+    /// below the soft limit, which can never be raised above the hard one.
+    pub(crate) static FD_LIMITS: RwLock<(usize, usize)> = RwLock::new((AX_FILE_LIMIT, AX_FILE_LIMIT));
+}
+
+/// Returns the current soft limit of open files.
+pub fn file_limit() -> usize {
+    FD_LIMITS.read().0
+}
+
+/// Returns the current hard limit of open files.
+pub fn file_hard_limit() -> usize {
+    FD_LIMITS.read().1
+}
+
+/// Sets the soft and hard limits of open files.
+///
+/// The hard limit can only be lowered, and the soft limit cannot exceed it.
+/// Lowering the soft limit below the number of open files is allowed, but no
+/// new fds can be allocated until enough of them are closed.
+pub fn set_file_limits(cur: usize, max: usize) -> LinuxResult {
+    let mut limits = FD_LIMITS.write();
+    if cur > max {
+        return Err(LinuxError::EINVAL);
+    }
+    if max > limits.1 {
+        return Err(LinuxError::EPERM);
+    }
+    *limits = (cur, max);
+    Ok(())
 }
 
 pub fn get_file_like(fd: c_int) -> LinuxResult<Arc<dyn FileLike>> {
@@ -34,8 +67,22 @@ pub fn get_file_like(fd: c_int) -> LinuxResult<Arc<dyn FileLike>> {
         .ok_or(LinuxError::EBADF)
 }
 
+/// Returns the path that `fd` was opened with.
+///
+/// The path is recorded at open time and is not updated if the file is
+/// renamed afterwards.
+pub fn fd_path(fd: c_int) -> LinuxResult<String> {
+    Ok(get_file_like(fd)?.path())
+}
+
 pub fn add_file_like(f: Arc<dyn FileLike>) -> LinuxResult<c_int> {
-    Ok(FD_TABLE.write().add(f).map_err(|_| LinuxError::EMFILE)? as c_int)
+    let mut table = FD_TABLE.write();
+    let fd = table.add(f).map_err(|_| LinuxError::EMFILE)?;
+    if fd >= file_limit() {
+        table.remove(fd);
+        return Err(LinuxError::EMFILE);
+    }
+    Ok(fd as c_int)
 }
 
 pub fn close_file_like(fd: c_int) -> LinuxResult {
@@ -82,7 +129,7 @@ pub fn sys_dup2(old_fd: c_int, new_fd: c_int) -> c_int {
                 return Ok(r);
             }
         }
-        if new_fd as usize >= AX_FILE_LIMIT {
+        if new_fd as usize >= file_limit() {
             return Err(LinuxError::EBADF);
         }
 
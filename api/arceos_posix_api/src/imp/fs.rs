@@ -1,4 +1,4 @@
-use alloc::sync::Arc;
+use alloc::{string::String, sync::Arc};
 use core::ffi::{c_char, c_int};
 
 use axerrno::{LinuxError, LinuxResult};
@@ -11,12 +11,14 @@ use crate::{ctypes, utils::char_ptr_to_str};
 
 pub struct File {
     inner: Mutex<axfs::fops::File>,
+    path: String,
 }
 
 impl File {
-    fn new(inner: axfs::fops::File) -> Self {
+    fn new(inner: axfs::fops::File, path: String) -> Self {
         Self {
             inner: Mutex::new(inner),
+            path,
         }
     }
 
@@ -42,21 +44,7 @@ impl FileLike for File {
     }
 
     fn stat(&self) -> LinuxResult<ctypes::stat> {
-        let metadata = self.inner.lock().get_attr()?;
-        let ty = metadata.file_type() as u8;
-        let perm = metadata.perm().bits() as u32;
-        let st_mode = ((ty as u32) << 12) | perm;
-        Ok(ctypes::stat {
-            st_ino: 1,
-            st_nlink: 1,
-            st_mode,
-            st_uid: 1000,
-            st_gid: 1000,
-            st_size: metadata.size() as _,
-            st_blocks: metadata.blocks() as _,
-            st_blksize: 512,
-            ..Default::default()
-        })
+        file_stat(&self.inner.lock())
     }
 
     fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
@@ -73,6 +61,29 @@ impl FileLike for File {
     fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
         Ok(())
     }
+
+    fn path(&self) -> String {
+        self.path.clone()
+    }
+}
+
+/// Get the metadata of an opened file.
+fn file_stat(file: &axfs::fops::File) -> LinuxResult<ctypes::stat> {
+    let metadata = file.get_attr()?;
+    let ty = metadata.file_type() as u8;
+    let perm = metadata.perm().bits() as u32;
+    let st_mode = ((ty as u32) << 12) | perm;
+    Ok(ctypes::stat {
+        st_ino: 1,
+        st_nlink: 1,
+        st_mode,
+        st_uid: 1000,
+        st_gid: 1000,
+        st_size: metadata.size() as _,
+        st_blocks: metadata.blocks() as _,
+        st_blksize: 512,
+        ..Default::default()
+    })
 }
 
 /// Convert open flags to [`OpenOptions`].
@@ -111,8 +122,9 @@ pub fn sys_open(filename: *const c_char, flags: c_int, mode: ctypes::mode_t) ->
     debug!("sys_open <= {:?} {:#o} {:#o}", filename, flags, mode);
     syscall_body!(sys_open, {
         let options = flags_to_options(flags, mode);
-        let file = axfs::fops::File::open(filename?, &options)?;
-        File::new(file).add_to_fd_table()
+        let filename = filename?;
+        let file = axfs::fops::File::open(filename, &options)?;
+        File::new(file, axfs::api::canonicalize(filename)?).add_to_fd_table()
     })
 }
 
@@ -146,8 +158,7 @@ pub unsafe fn sys_stat(path: *const c_char, buf: *mut ctypes::stat) -> c_int {
         let mut options = OpenOptions::new();
         options.read(true);
         let file = axfs::fops::File::open(path?, &options)?;
-        let st = File::new(file).stat()?;
-        unsafe { *buf = st };
+        unsafe { *buf = file_stat(&file)? };
         Ok(0)
     })
 }
@@ -203,6 +214,26 @@ pub fn sys_getcwd(buf: *mut c_char, size: usize) -> *mut c_char {
     })
 }
 
+/// Change the current directory to the directory referred to by `fd`.
+///
+/// Return 0 if success.
+pub fn sys_fchdir(fd: c_int) -> c_int {
+    debug!("sys_fchdir <= {}", fd);
+    syscall_body!(sys_fchdir, {
+        // only a `File` can refer to a directory, e.g. not a pipe or socket
+        let file = get_file_like(fd)?
+            .into_any()
+            .downcast::<File>()
+            .map_err(|_| LinuxError::ENOTDIR)?;
+        let inner = file.inner.lock();
+        if !inner.get_attr()?.is_dir() {
+            return Err(LinuxError::ENOTDIR);
+        }
+        inner.set_as_current_dir(&file.path)?;
+        Ok(0)
+    })
+}
+
 /// Rename `old` to `new`
 /// If new exists, it is first removed.
 ///
@@ -4,6 +4,7 @@
 
 use alloc::collections::BTreeMap;
 use alloc::collections::btree_map::Entry;
+use alloc::string::String;
 use alloc::sync::Arc;
 use core::{ffi::c_int, time::Duration};
 
@@ -135,6 +136,10 @@ impl FileLike for EpollInstance {
     fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
         Ok(())
     }
+
+    fn path(&self) -> String {
+        "anon_inode:[eventpoll]".into()
+    }
 }
 
 /// Creates a new epoll instance.
@@ -0,0 +1,88 @@
+use core::ffi::c_int;
+use core::time::Duration;
+
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::wall_time;
+
+use crate::ctypes;
+use crate::imp::fd_ops::{file_limit, get_file_like};
+
+const POLLIN: i16 = ctypes::POLLIN as i16;
+const POLLOUT: i16 = ctypes::POLLOUT as i16;
+const POLLERR: i16 = ctypes::POLLERR as i16;
+const POLLNVAL: i16 = ctypes::POLLNVAL as i16;
+
+/// Fills in `revents` of every entry, returns the number of entries with
+/// non-zero `revents`.
+fn poll_all(fds: &mut [ctypes::pollfd]) -> LinuxResult<usize> {
+    let mut res_num = 0;
+    for pfd in fds.iter_mut() {
+        pfd.revents = 0;
+        // negative fds are ignored
+        if pfd.fd < 0 {
+            continue;
+        }
+        match get_file_like(pfd.fd) {
+            Ok(f) => match f.poll() {
+                Ok(state) => {
+                    if state.readable && pfd.events & POLLIN != 0 {
+                        pfd.revents |= POLLIN;
+                    }
+                    if state.writable && pfd.events & POLLOUT != 0 {
+                        pfd.revents |= POLLOUT;
+                    }
+                }
+                Err(e) => {
+                    debug!("    error: {} {:?}", pfd.fd, e);
+                    pfd.revents |= POLLERR;
+                }
+            },
+            Err(LinuxError::EBADF) => pfd.revents |= POLLNVAL,
+            Err(e) => return Err(e),
+        }
+        if pfd.revents != 0 {
+            res_num += 1;
+        }
+    }
+    Ok(res_num)
+}
+
+/// Wait for some event on a set of file descriptors.
+///
+/// `timeout` is in milliseconds, a negative value means an infinite timeout.
+pub unsafe fn sys_poll(fds: *mut ctypes::pollfd, nfds: ctypes::nfds_t, timeout: c_int) -> c_int {
+    debug!(
+        "sys_poll <= fds: {:#x}, nfds: {}, timeout: {}",
+        fds as usize, nfds, timeout
+    );
+
+    syscall_body!(sys_poll, {
+        if nfds > file_limit() as _ {
+            return Err(LinuxError::EINVAL);
+        }
+        if nfds > 0 && fds.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let fds: &mut [ctypes::pollfd] = if nfds == 0 {
+            &mut []
+        } else {
+            unsafe { core::slice::from_raw_parts_mut(fds, nfds as usize) }
+        };
+        let deadline =
+            (!timeout.is_negative()).then(|| wall_time() + Duration::from_millis(timeout as u64));
+        loop {
+            #[cfg(feature = "net")]
+            axnet::poll_interfaces();
+            let res = poll_all(fds)?;
+            if res > 0 {
+                return Ok(res as c_int);
+            }
+
+            if deadline.is_some_and(|ddl| wall_time() >= ddl) {
+                debug!("    timeout!");
+                return Ok(0);
+            }
+            crate::sys_sched_yield();
+        }
+    })
+}
@@ -10,7 +10,7 @@ pub mod time;
 pub mod fd_ops;
 #[cfg(feature = "fs")]
 pub mod fs;
-#[cfg(any(feature = "select", feature = "epoll"))]
+#[cfg(any(feature = "select", feature = "poll", feature = "epoll"))]
 pub mod io_mpx;
 #[cfg(feature = "net")]
 pub mod net;
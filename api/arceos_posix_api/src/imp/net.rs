@@ -1,4 +1,4 @@
-use alloc::{sync::Arc, vec, vec::Vec};
+use alloc::{format, string::String, sync::Arc, vec, vec::Vec};
 use core::ffi::{c_char, c_int, c_void};
 use core::mem::size_of;
 use core::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
@@ -168,6 +168,10 @@ impl FileLike for Socket {
         }
         Ok(())
     }
+
+    fn path(&self) -> String {
+        format!("socket:[{:#x}]", self as *const Self as usize)
+    }
 }
 
 impl From<SocketAddrV4> for ctypes::sockaddr_in {
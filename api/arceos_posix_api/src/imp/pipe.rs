@@ -1,4 +1,4 @@
-use alloc::sync::Arc;
+use alloc::{format, string::String, sync::Arc};
 use core::ffi::c_int;
 
 use axerrno::{LinuxError, LinuxResult};
@@ -188,6 +188,11 @@ impl FileLike for Pipe {
     fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
         Ok(())
     }
+
+    fn path(&self) -> String {
+        // both ends share the ring buffer, so they get the same name
+        format!("pipe:[{:#x}]", Arc::as_ptr(&self.buffer) as usize)
+    }
 }
 
 /// Create a pipe
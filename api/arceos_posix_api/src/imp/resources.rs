@@ -4,6 +4,10 @@ use core::ffi::c_int;
 
 /// Get resource limitations
 ///
+/// `RLIMIT_NPROC` is not supported, as ArceOS runs a single process with no
+/// `fork`, and `prlimit64` is left to the syscall layer on top of this crate,
+/// which can implement it for the calling process with these two functions.
+///
 /// TODO: support more resource types
 pub unsafe fn sys_getrlimit(resource: c_int, rlimits: *mut ctypes::rlimit) -> c_int {
     debug!("sys_getrlimit <= {} {:#x}", resource, rlimits as usize);
@@ -24,8 +28,8 @@ pub unsafe fn sys_getrlimit(resource: c_int, rlimits: *mut ctypes::rlimit) -> c_
             },
             #[cfg(feature = "fd")]
             ctypes::RLIMIT_NOFILE => unsafe {
-                (*rlimits).rlim_cur = super::fd_ops::AX_FILE_LIMIT as _;
-                (*rlimits).rlim_max = super::fd_ops::AX_FILE_LIMIT as _;
+                (*rlimits).rlim_cur = super::fd_ops::file_limit() as _;
+                (*rlimits).rlim_max = super::fd_ops::file_hard_limit() as _;
             },
             _ => {}
         }
@@ -35,6 +39,10 @@ pub unsafe fn sys_getrlimit(resource: c_int, rlimits: *mut ctypes::rlimit) -> c_
 
 /// Set resource limitations
 ///
+/// Only `RLIMIT_NOFILE` is actually changed. Its hard limit starts at the
+/// size of the fd table and can only be lowered, and its soft limit cannot be
+/// raised above the hard one.
+///
 /// TODO: support more resource types
 pub unsafe fn sys_setrlimit(resource: c_int, rlimits: *mut crate::ctypes::rlimit) -> c_int {
     debug!("sys_setrlimit <= {} {:#x}", resource, rlimits as usize);
@@ -45,7 +53,14 @@ pub unsafe fn sys_setrlimit(resource: c_int, rlimits: *mut crate::ctypes::rlimit
             crate::ctypes::RLIMIT_NOFILE => {}
             _ => return Err(LinuxError::EINVAL),
         }
-        // Currently do not support set resources
+        if rlimits.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        #[cfg(feature = "fd")]
+        if resource as u32 == ctypes::RLIMIT_NOFILE {
+            let (cur, max) = unsafe { ((*rlimits).rlim_cur, (*rlimits).rlim_max) };
+            super::fd_ops::set_file_limits(cur as _, max as _)?;
+        }
         Ok(0)
     })
 }
@@ -3,7 +3,12 @@ use axio::{BufReader, prelude::*};
 use axsync::Mutex;
 
 #[cfg(feature = "fd")]
-use {alloc::sync::Arc, axerrno::LinuxError, axerrno::LinuxResult, axio::PollState};
+use {
+    alloc::{string::String, sync::Arc},
+    axerrno::LinuxError,
+    axerrno::LinuxResult,
+    axio::PollState,
+};
 
 fn console_read_bytes(buf: &mut [u8]) -> AxResult<usize> {
     let len = axhal::console::read_bytes(buf);
@@ -136,6 +141,10 @@ impl super::fd_ops::FileLike for Stdin {
     fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
         Ok(())
     }
+
+    fn path(&self) -> String {
+        "/dev/stdin".into()
+    }
 }
 
 #[cfg(feature = "fd")]
@@ -172,4 +181,8 @@ impl super::fd_ops::FileLike for Stdout {
     fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
         Ok(())
     }
+
+    fn path(&self) -> String {
+        "/dev/stdout".into()
+    }
 }
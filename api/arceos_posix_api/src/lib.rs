@@ -37,11 +37,15 @@ pub use imp::task::{sys_exit, sys_getpid, sys_sched_yield};
 pub use imp::time::{sys_clock_gettime, sys_nanosleep};
 
 #[cfg(feature = "fd")]
-pub use imp::fd_ops::{sys_close, sys_dup, sys_dup2, sys_fcntl};
+pub use imp::fd_ops::{fd_path, sys_close, sys_dup, sys_dup2, sys_fcntl};
 #[cfg(feature = "fs")]
-pub use imp::fs::{sys_fstat, sys_getcwd, sys_lseek, sys_lstat, sys_open, sys_rename, sys_stat};
+pub use imp::fs::{
+    sys_fchdir, sys_fstat, sys_getcwd, sys_lseek, sys_lstat, sys_open, sys_rename, sys_stat,
+};
 #[cfg(feature = "select")]
 pub use imp::io_mpx::sys_select;
+#[cfg(feature = "poll")]
+pub use imp::io_mpx::sys_poll;
 #[cfg(feature = "epoll")]
 pub use imp::io_mpx::{sys_epoll_create, sys_epoll_ctl, sys_epoll_wait};
 #[cfg(feature = "net")]
@@ -0,0 +1,168 @@
+#![cfg(feature = "fs")]
+
+use std::ffi::CStr;
+
+use arceos_posix_api::{self as api, ctypes};
+use axdriver::AxDeviceContainer;
+use axdriver_block::ramdisk::RamDisk;
+use axerrno::LinuxError;
+use axfs::api as fs;
+
+const IMG_PATH: &str = "../../modules/axfs/resources/fat16.img";
+
+fn open(path: &CStr, flags: u32) -> i32 {
+    api::sys_open(path.as_ptr(), flags as _, 0)
+}
+
+fn read_to_string(fd: i32) -> String {
+    let mut buf = [0u8; 64];
+    let n = api::sys_read(fd, buf.as_mut_ptr() as _, buf.len());
+    assert!(n >= 0);
+    String::from_utf8(buf[..n as usize].to_vec()).unwrap()
+}
+
+fn test_fchdir() {
+    fs::create_dir("/dir").unwrap();
+    fs::write("/dir/a.txt", "a").unwrap();
+
+    let file = open(c"/dir/a.txt", ctypes::O_RDONLY);
+    assert_eq!(api::sys_fchdir(file), -LinuxError::ENOTDIR.code());
+
+    let dir = open(c"/dir", ctypes::O_RDONLY);
+    assert!(dir >= 0);
+    assert_eq!(api::sys_fchdir(dir), 0);
+    assert_eq!(fs::current_dir().unwrap(), "/dir/");
+    let fd = open(c"a.txt", ctypes::O_RDONLY);
+    assert!(fd >= 0);
+    assert_eq!(read_to_string(fd), "a");
+
+    fs::set_current_dir("/").unwrap();
+    for fd in [file, dir, fd] {
+        assert_eq!(api::sys_close(fd), 0);
+    }
+
+    #[cfg(feature = "pipe")]
+    {
+        let mut fds = [0; 2];
+        assert_eq!(api::sys_pipe(&mut fds), 0);
+        assert_eq!(api::sys_fchdir(fds[0]), -LinuxError::ENOTDIR.code());
+        for fd in fds {
+            assert_eq!(api::sys_close(fd), 0);
+        }
+    }
+}
+
+fn test_fd_path() {
+    fs::write("/dir/b.txt", "b").unwrap();
+    fs::set_current_dir("/dir").unwrap();
+    let fd = open(c"./b.txt", ctypes::O_RDONLY);
+    assert_eq!(api::fd_path(fd).unwrap(), "/dir/b.txt");
+    fs::set_current_dir("/").unwrap();
+    assert_eq!(api::fd_path(fd).unwrap(), "/dir/b.txt");
+
+    // the recorded path is not updated by a rename, while the file still is
+    // the one that was opened
+    assert_eq!(
+        api::sys_rename(c"/dir/b.txt".as_ptr(), c"/dir/c.txt".as_ptr()),
+        0
+    );
+    assert_eq!(api::fd_path(fd).unwrap(), "/dir/b.txt");
+    assert_eq!(read_to_string(fd), "b");
+    let mut st = ctypes::stat::default();
+    assert_eq!(unsafe { api::sys_stat(c"/dir/c.txt".as_ptr(), &mut st) }, 0);
+    assert_eq!(st.st_size, 1);
+
+    assert_eq!(api::sys_close(fd), 0);
+    assert_eq!(api::fd_path(fd), Err(LinuxError::EBADF));
+}
+
+#[cfg(all(feature = "pipe", feature = "poll", feature = "multitask"))]
+fn test_poll_pipe() {
+    let mut fds = [0; 2];
+    assert_eq!(api::sys_pipe(&mut fds), 0);
+    let mut pfd = ctypes::pollfd {
+        fd: fds[0],
+        events: ctypes::POLLIN as _,
+        revents: 0,
+    };
+
+    // nothing is written yet, a zero timeout returns at once
+    assert_eq!(unsafe { api::sys_poll(&mut pfd, 1, 0) }, 0);
+    assert_eq!(pfd.revents, 0);
+
+    // the writer only runs once the poll loop yields
+    let write_end = fds[1];
+    let writer = axtask::spawn(move || {
+        assert_eq!(api::sys_write(write_end, b"x".as_ptr() as _, 1), 1);
+    });
+    assert_eq!(unsafe { api::sys_poll(&mut pfd, 1, 1000) }, 1);
+    assert_eq!(pfd.revents, ctypes::POLLIN as _);
+    assert_eq!(writer.join(), Some(0));
+    assert_eq!(read_to_string(fds[0]), "x");
+
+    for fd in fds {
+        assert_eq!(api::sys_close(fd), 0);
+    }
+}
+
+fn getrlimit() -> (u64, u64) {
+    let mut rlim = ctypes::rlimit::default();
+    let res = unsafe { api::sys_getrlimit(ctypes::RLIMIT_NOFILE as _, &mut rlim) };
+    assert_eq!(res, 0);
+    (rlim.rlim_cur as _, rlim.rlim_max as _)
+}
+
+fn setrlimit(cur: u64, max: u64) -> i32 {
+    let mut rlim = ctypes::rlimit {
+        rlim_cur: cur as _,
+        rlim_max: max as _,
+    };
+    unsafe { api::sys_setrlimit(ctypes::RLIMIT_NOFILE as _, &mut rlim) }
+}
+
+/// Lowers the hard limit for good, so it must run last.
+fn test_rlimit_nofile() {
+    assert_eq!(getrlimit(), (1024, 1024));
+    assert_eq!(setrlimit(8, 16), 0);
+    assert_eq!(getrlimit(), (8, 16));
+
+    // fds 0, 1 and 2 are taken by stdio
+    let fds = (3..8)
+        .map(|_| open(c"/dir/a.txt", ctypes::O_RDONLY))
+        .collect::<Vec<_>>();
+    assert_eq!(fds, [3, 4, 5, 6, 7]);
+    let emfile = -LinuxError::EMFILE.code();
+    assert_eq!(open(c"/dir/a.txt", ctypes::O_RDONLY), emfile);
+    #[cfg(feature = "poll")]
+    {
+        let mut fds = [ctypes::pollfd::default(); 9];
+        let res = unsafe { api::sys_poll(fds.as_mut_ptr(), fds.len() as _, 0) };
+        assert_eq!(res, -LinuxError::EINVAL.code());
+    }
+
+    // the soft limit cannot exceed the hard one, which cannot be raised
+    assert_eq!(setrlimit(32, 16), -LinuxError::EINVAL.code());
+    assert_eq!(setrlimit(16, 32), -LinuxError::EPERM.code());
+    assert_eq!(getrlimit(), (8, 16));
+    assert_eq!(setrlimit(16, 16), 0);
+    let fd = open(c"/dir/a.txt", ctypes::O_RDONLY);
+    assert_eq!(fd, 8);
+
+    for fd in fds.into_iter().chain([fd]) {
+        assert_eq!(api::sys_close(fd), 0);
+    }
+}
+
+#[test]
+fn test_fs() {
+    // the FAT image of the axfs tests, which supports renaming
+    let data = std::fs::read(IMG_PATH).expect("failed to load disk image");
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems(AxDeviceContainer::from_one(RamDisk::from(&data)));
+
+    test_fchdir();
+    test_fd_path();
+    #[cfg(all(feature = "pipe", feature = "poll", feature = "multitask"))]
+    test_poll_pipe();
+    test_rlimit_nofile();
+}
@@ -1,26 +1,149 @@
-//! Bitmap allocator wrapper for embedded/host use.
+//! Bitmap page allocator implementation.
 //!
-//! This wraps the `allocator::BitmapPageAllocator` from the shared
-//! `allocator` crate to provide a `PageAllocator`-compatible implementation
-//! usable for runtime selection. It's lightweight and mirrors the behavior
-//! of the existing page allocator used by `GlobalAllocator`.
+//! Keeps one bit per page (1 = free, 0 = allocated) and serves requests by
+//! first-fit search over contiguous free runs.
+//! - Small and predictable footprint, suited to tiny embedded boards.
+//! - Tracks allocations in a map so deallocation frees the full allocated run.
+//! - Supports `alloc_pages`, `alloc_pages_at` (exact start), and `dealloc_pages`.
 
-use allocator::{AllocError, BitmapPageAllocator, BaseAllocator, PageAllocator as AllocatorPageAllocator};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use allocator::AllocError;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use kspin::SpinNoIrq;
-use super::PageAllocator;
+use memory_addr::is_aligned;
+use super::{AllocStats, PageAllocator};
 
 const PAGE_SIZE: usize = 4096;
 
 pub struct BitmapAllocator {
-    inner: SpinNoIrq<BitmapPageAllocator<PAGE_SIZE>>,
+    base: AtomicUsize,
+    total_pages: AtomicUsize,
+
+    /// 1 bit per page, 1 = free, 0 = allocated.
+    bitmap: SpinNoIrq<Vec<u8>>,
+
+    /// Track allocations: start_index -> size_in_pages.
+    alloc_map: SpinNoIrq<BTreeMap<usize, usize>>,
+
+    used_pages: SpinNoIrq<usize>,
 }
 
 impl BitmapAllocator {
     pub fn new() -> Self {
         Self {
-            inner: SpinNoIrq::new(BitmapPageAllocator::new()),
+            base: AtomicUsize::new(0),
+            total_pages: AtomicUsize::new(0),
+            bitmap: SpinNoIrq::new(Vec::new()),
+            alloc_map: SpinNoIrq::new(BTreeMap::new()),
+            used_pages: SpinNoIrq::new(0),
+        }
+    }
+
+    fn base(&self) -> usize {
+        self.base.load(Ordering::Acquire)
+    }
+
+    fn total_pages(&self) -> usize {
+        self.total_pages.load(Ordering::Acquire)
+    }
+
+    /// Record an allocation, keeping `alloc_map` and `used_pages` in sync.
+    fn record_alloc(&self, idx: usize, num_pages: usize) {
+        let mut alloc_map = self.alloc_map.lock();
+        alloc_map.insert(idx, num_pages);
+        *self.used_pages.lock() += num_pages;
+    }
+
+    /// Resize the allocation at page `idx` to `new_pages` without moving it,
+    /// growing into the free pages right after it. Returns `false` if it
+    /// cannot be resized in place.
+    fn resize_in_place(&self, idx: usize, new_pages: usize) -> bool {
+        let mut alloc_map = self.alloc_map.lock();
+        let size = match alloc_map.get(&idx) {
+            Some(&size) => size,
+            None => return false,
+        };
+        let end_idx = idx + size;
+        let new_end_idx = idx + new_pages;
+
+        let mut bitmap = self.bitmap.lock();
+        if new_pages > size {
+            if new_end_idx > self.total_pages()
+                || !(end_idx..new_end_idx).all(|i| Self::is_free(&bitmap, i))
+            {
+                return false;
+            }
+            Self::mark_allocated(&mut bitmap, end_idx, new_pages - size);
+        } else {
+            Self::mark_free(&mut bitmap, new_end_idx, size - new_pages);
+        }
+        drop(bitmap);
+
+        alloc_map.insert(idx, new_pages);
+        let mut used_pages = self.used_pages.lock();
+        *used_pages = *used_pages + new_pages - size;
+        true
+    }
+
+    fn is_free(bitmap: &[u8], idx: usize) -> bool {
+        bitmap[idx / 8] & (1u8 << (idx % 8)) != 0
+    }
+
+    /// Mark pages in bitmap as free (bit = 1).
+    fn mark_free(bitmap: &mut [u8], start_idx: usize, count: usize) {
+        for i in start_idx..start_idx + count {
+            bitmap[i / 8] |= 1u8 << (i % 8);
+        }
+    }
+
+    /// Mark pages in bitmap as allocated (bit = 0).
+    fn mark_allocated(bitmap: &mut [u8], start_idx: usize, count: usize) {
+        for i in start_idx..start_idx + count {
+            bitmap[i / 8] &= !(1u8 << (i % 8));
         }
     }
+
+    /// Find the first run of `needed` free pages whose start address is
+    /// aligned to `align_pow2`.
+    fn find_free_run(&self, bitmap: &[u8], needed: usize, align_pow2: usize) -> Option<usize> {
+        let mut start = 0;
+        let mut len = 0;
+        for idx in 0..self.total_pages() {
+            if !Self::is_free(bitmap, idx) {
+                len = 0;
+                continue;
+            }
+            if len == 0 {
+                // a run can only start at an aligned page
+                if !is_aligned(self.base() + idx * PAGE_SIZE, align_pow2) {
+                    continue;
+                }
+                start = idx;
+            }
+            len += 1;
+            if len == needed {
+                return Some(start);
+            }
+        }
+        None
+    }
+
+    /// Size (in pages) of the longest free run.
+    fn largest_free_run(&self) -> usize {
+        let bitmap = self.bitmap.lock();
+        let mut largest = 0;
+        let mut len = 0;
+        for idx in 0..self.total_pages() {
+            if Self::is_free(&bitmap, idx) {
+                len += 1;
+                largest = largest.max(len);
+            } else {
+                len = 0;
+            }
+        }
+        largest
+    }
 }
 
 impl PageAllocator for BitmapAllocator {
@@ -29,12 +152,60 @@ impl PageAllocator for BitmapAllocator {
     }
 
     fn init(&self, start_vaddr: usize, size: usize) -> Result<(), AllocError> {
-        self.inner.lock().init(start_vaddr, size);
+        let end = (start_vaddr + size) & !(PAGE_SIZE - 1);
+        let start = (start_vaddr + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+        if end <= start {
+            return Err(AllocError::InvalidParam);
+        }
+        let total_pages = (end - start) / PAGE_SIZE;
+        if total_pages == 0 {
+            return Err(AllocError::InvalidParam);
+        }
+
+        // All pages are free (bit = 1), except the padding bits of the last byte
+        let bitmap_size = total_pages.div_ceil(8);
+        let mut bitmap = Vec::new();
+        bitmap.resize(bitmap_size, 0xFFu8);
+        if total_pages % 8 != 0 {
+            let unused_bits = 8 - (total_pages % 8);
+            bitmap[bitmap_size - 1] &= 0xFFu8 >> unused_bits;
+        }
+
+        // publish the new region with the bitmap lock held, so an allocation
+        // never sees a bitmap and bounds that do not match
+        let mut bitmap_guard = self.bitmap.lock();
+        *bitmap_guard = bitmap;
+        self.base.store(start, Ordering::Release);
+        self.total_pages.store(total_pages, Ordering::Release);
+        drop(bitmap_guard);
+
+        let mut alloc_map = self.alloc_map.lock();
+        alloc_map.clear();
+        *self.used_pages.lock() = 0;
+
         Ok(())
     }
 
     fn alloc_pages(&self, num_pages: usize, align_pow2: usize) -> Result<usize, AllocError> {
-        self.inner.lock().alloc_pages(num_pages, align_pow2)
+        if num_pages == 0 {
+            return Err(AllocError::InvalidParam);
+        }
+        if align_pow2 < PAGE_SIZE || !align_pow2.is_power_of_two() {
+            return Err(AllocError::InvalidParam);
+        }
+        if num_pages > self.total_pages() {
+            return Err(AllocError::NoMemory);
+        }
+
+        let mut bitmap = self.bitmap.lock();
+        let idx = self
+            .find_free_run(&bitmap, num_pages, align_pow2)
+            .ok_or(AllocError::NoMemory)?;
+        Self::mark_allocated(&mut bitmap, idx, num_pages);
+        drop(bitmap);
+
+        self.record_alloc(idx, num_pages);
+        Ok(self.base() + idx * PAGE_SIZE)
     }
 
     fn alloc_pages_at(
@@ -43,16 +214,99 @@ impl PageAllocator for BitmapAllocator {
         num_pages: usize,
         align_pow2: usize,
     ) -> Result<usize, AllocError> {
-        self.inner.lock().alloc_pages_at(start, num_pages, align_pow2)
+        if num_pages == 0 {
+            return Err(AllocError::InvalidParam);
+        }
+        if align_pow2 < PAGE_SIZE || !align_pow2.is_power_of_two() {
+            return Err(AllocError::InvalidParam);
+        }
+        if start < self.base() || start >= self.base() + self.total_pages() * PAGE_SIZE {
+            return Err(AllocError::InvalidParam);
+        }
+        if !is_aligned(start, align_pow2) {
+            return Err(AllocError::InvalidParam);
+        }
+
+        let idx = (start - self.base()) / PAGE_SIZE;
+        if idx + num_pages > self.total_pages() {
+            return Err(AllocError::NoMemory);
+        }
+
+        let mut bitmap = self.bitmap.lock();
+        if !(idx..idx + num_pages).all(|i| Self::is_free(&bitmap, i)) {
+            return Err(AllocError::NoMemory);
+        }
+        Self::mark_allocated(&mut bitmap, idx, num_pages);
+        drop(bitmap);
+
+        self.record_alloc(idx, num_pages);
+        Ok(start)
     }
 
     fn dealloc_pages(&self, pos: usize, num_pages: usize) {
-        self.inner.lock().dealloc_pages(pos, num_pages)
+        if pos < self.base() || pos >= self.base() + self.total_pages() * PAGE_SIZE {
+            warn!("bitmap: dealloc of {:#x} outside the managed region", pos);
+            return;
+        }
+        if !is_aligned(pos, PAGE_SIZE) {
+            warn!("bitmap: dealloc of unaligned address {:#x}", pos);
+            return;
+        }
+
+        let idx = (pos - self.base()) / PAGE_SIZE;
+
+        // Look up the allocation
+        let mut alloc_map = self.alloc_map.lock();
+        let size = match alloc_map.get(&idx) {
+            Some(&size) => size,
+            None => {
+                warn!("bitmap: dealloc of unallocated address {:#x}", pos);
+                return;
+            }
+        };
+        if num_pages != 0 && num_pages != size {
+            warn!("bitmap: dealloc of {} pages at {:#x}, but {} were allocated", num_pages, pos, size);
+            return;
+        }
+        alloc_map.remove(&idx);
+        *self.used_pages.lock() -= size;
+        drop(alloc_map);
+
+        Self::mark_free(&mut self.bitmap.lock(), idx, size);
+    }
+
+    fn realloc_pages(
+        &self,
+        pos: usize,
+        old_pages: usize,
+        new_pages: usize,
+        align_pow2: usize,
+    ) -> Result<usize, AllocError> {
+        if new_pages == 0 {
+            return Err(AllocError::InvalidParam);
+        }
+        if align_pow2 < PAGE_SIZE || !align_pow2.is_power_of_two() {
+            return Err(AllocError::InvalidParam);
+        }
+        if pos < self.base() || pos >= self.base() + self.total_pages() * PAGE_SIZE {
+            return Err(AllocError::InvalidParam);
+        }
+        if !is_aligned(pos, PAGE_SIZE) {
+            return Err(AllocError::InvalidParam);
+        }
+
+        let idx = (pos - self.base()) / PAGE_SIZE;
+        match self.alloc_map.lock().get(&idx) {
+            Some(&size) if old_pages == 0 || old_pages == size => {}
+            _ => return Err(AllocError::InvalidParam),
+        }
+        if is_aligned(pos, align_pow2) && self.resize_in_place(idx, new_pages) {
+            return Ok(pos);
+        }
+        self.alloc_pages(new_pages, align_pow2)
     }
 
-    fn get_stats(&self) -> (f64, usize) {
-        // BitmapAllocator doesn't track fragmentation granularly;
-        // for now return default (allocator doesn't expose stats)
-        (0.0, 0)
+    fn stats(&self) -> AllocStats {
+        AllocStats::new(self.total_pages(), *self.used_pages.lock(), self.largest_free_run())
     }
 }
@@ -5,25 +5,34 @@
 //! - Tracks allocations in a map so deallocation frees the full allocated block.
 //! - Supports `alloc_pages`, `alloc_pages_at` (exact start), and `dealloc_pages`.
 
-use alloc::collections::BTreeMap;
+use alloc::collections::{BTreeMap, BTreeSet};
 use alloc::vec::Vec;
 use allocator::AllocError;
 use core::cmp;
 use kspin::SpinNoIrq;
 use memory_addr::is_aligned;
-use super::PageAllocator;
+use super::{AllocStats, PageAllocator};
 
 const PAGE_SIZE: usize = 4096;
 
 pub struct BuddyAllocator {
+    /// All the state is behind one lock, so `init` can never run between the
+    /// steps of another operation.
+    inner: SpinNoIrq<BuddyInner>,
+}
+
+struct BuddyInner {
     base: usize,
     total_pages: usize,
     max_order: usize,
-    /// free_lists[order] contains start indices (in pages) of free blocks of size 2^order
-    free_lists: SpinNoIrq<Vec<Vec<usize>>>,
+    /// free_lists[order] contains start indices (in pages) of free blocks of size 2^order,
+    /// kept sorted so the lowest address is handed out first
+    free_lists: Vec<BTreeSet<usize>>,
     /// allocation map: start_index -> order
-    alloc_map: SpinNoIrq<BTreeMap<usize, usize>>,
-    used_pages: SpinNoIrq<usize>,
+    alloc_map: BTreeMap<usize, usize>,
+    /// runs assembled from several adjacent blocks: start_index -> pages
+    runs: BTreeMap<usize, usize>,
+    used_pages: usize,
 }
 
 fn ceil_log2(n: usize) -> usize {
@@ -38,38 +47,194 @@ fn ceil_log2(n: usize) -> usize {
 }
 
 impl BuddyAllocator {
-    pub fn new() -> Self {
+    pub const fn new() -> Self {
+        Self {
+            inner: SpinNoIrq::new(BuddyInner::new()),
+        }
+    }
+
+    /// Allocate `num_pages` contiguous pages, even if no single free block is
+    /// large enough.
+    ///
+    /// It first tries a normal allocation. If there is no large enough block,
+    /// it looks for a run of adjacent free blocks covering `num_pages` and
+    /// reserves each of them. The whole run is released by a single
+    /// `dealloc_pages` on the returned address.
+    pub fn alloc_contiguous_best_effort(&self, num_pages: usize) -> Result<usize, AllocError> {
+        if num_pages == 0 { return Err(AllocError::InvalidParam); }
+        let mut inner = self.inner.lock();
+        if let Some(idx) = inner.alloc(num_pages) {
+            return Ok(inner.base + idx * PAGE_SIZE);
+        }
+
+        // the blocks are taken under the same lock as the search, so they
+        // are all still free
+        let blocks = inner.find_free_run(num_pages).ok_or(AllocError::NoMemory)?;
+        for &(idx, order) in blocks.iter() {
+            inner.remove_free_exact(order, idx);
+            inner.record_alloc(idx, order);
+        }
+
+        let start_idx = blocks[0].0;
+        let pages = blocks.iter().map(|&(_, order)| 1usize << order).sum();
+        inner.runs.insert(start_idx, pages);
+        Ok(inner.base + start_idx * PAGE_SIZE)
+    }
+}
+
+impl BuddyInner {
+    const fn new() -> Self {
         Self {
             base: 0,
             total_pages: 0,
             max_order: 0,
-            free_lists: SpinNoIrq::new(Vec::new()),
-            alloc_map: SpinNoIrq::new(BTreeMap::new()),
-            used_pages: SpinNoIrq::new(0),
+            free_lists: Vec::new(),
+            alloc_map: BTreeMap::new(),
+            runs: BTreeMap::new(),
+            used_pages: 0,
         }
     }
 
-    fn push_free(&self, order: usize, idx: usize) {
-        let mut lists = self.free_lists.lock();
-        if order >= lists.len() {
-            lists.resize(order + 1, Vec::new());
+    fn contains(&self, pos: usize) -> bool {
+        pos >= self.base && pos < self.base + self.total_pages * PAGE_SIZE
+    }
+
+    /// Record an allocation, keeping `alloc_map` and `used_pages` in sync.
+    fn record_alloc(&mut self, idx: usize, order: usize) {
+        self.alloc_map.insert(idx, order);
+        self.used_pages += 1usize << order;
+    }
+
+    /// Allocate a block of at least `num_pages` pages, splitting a larger one
+    /// if needed. Returns its start index.
+    fn alloc(&mut self, num_pages: usize) -> Option<usize> {
+        let needed = num_pages.next_power_of_two();
+        let order = ceil_log2(needed);
+        let mut o = order;
+        while o <= self.max_order {
+            if let Some(idx) = self.pop_free(o) {
+                let mut cur_order = o;
+                while cur_order > order {
+                    cur_order -= 1;
+                    self.push_free(cur_order, idx + (1usize << cur_order));
+                }
+                self.record_alloc(idx, order);
+                return Some(idx);
+            }
+            o += 1;
         }
-        lists[order].push(idx);
+        None
     }
 
-    fn pop_free(&self, order: usize) -> Option<usize> {
-        let mut lists = self.free_lists.lock();
-        if order >= lists.len() { return None; }
-        lists[order].pop()
+    /// Return the `(start_index, pages)` of the run covering page `idx`.
+    fn run_covering(&self, idx: usize) -> Option<(usize, usize)> {
+        self.runs
+            .range(..=idx)
+            .next_back()
+            .map(|(&start, &pages)| (start, pages))
+            .filter(|&(start, pages)| idx < start + pages)
     }
 
-    fn remove_free_exact(&self, order: usize, idx: usize) -> bool {
-        let mut lists = self.free_lists.lock();
-        if order >= lists.len() { return false; }
-        if let Some(pos) = lists[order].iter().position(|&x| x == idx) {
-            lists[order].swap_remove(pos);
-            true
-        } else { false }
+    /// Resize the block at page `idx` to hold `new_pages` pages without moving
+    /// it. Shrinking splits off the upper halves; growing absorbs the upper
+    /// buddy at each order, which must be free. Returns `false` if the block
+    /// cannot be resized in place.
+    fn resize_in_place(&mut self, idx: usize, new_pages: usize) -> bool {
+        let order = match self.alloc_map.get(&idx) {
+            Some(&order) => order,
+            None => return false,
+        };
+        let new_order = ceil_log2(new_pages.next_power_of_two());
+        if new_order > self.max_order {
+            return false;
+        }
+
+        if new_order < order {
+            for o in (new_order..order).rev() {
+                self.push_free(o, idx + (1usize << o));
+            }
+        } else if new_order > order {
+            let mut taken: Vec<(usize, usize)> = Vec::new();
+            for o in order..new_order {
+                let buddy_idx = idx + (1usize << o);
+                // `idx` must stay the lower half of every merged block
+                if idx & (1usize << o) != 0 || !self.remove_free_exact(o, buddy_idx) {
+                    for &(o, buddy_idx) in taken.iter() {
+                        self.push_free(o, buddy_idx);
+                    }
+                    return false;
+                }
+                taken.push((o, buddy_idx));
+            }
+        }
+
+        self.alloc_map.insert(idx, new_order);
+        self.used_pages = self.used_pages + (1usize << new_order) - (1usize << order);
+        true
+    }
+
+    /// Find the first run of adjacent free blocks covering `num_pages`.
+    /// Returns the `(start_index, order)` of each block in the run.
+    fn find_free_run(&self, num_pages: usize) -> Option<Vec<(usize, usize)>> {
+        let mut free: Vec<(usize, usize)> = self
+            .free_lists
+            .iter()
+            .enumerate()
+            .flat_map(|(order, list)| list.iter().map(move |&idx| (idx, order)))
+            .collect();
+        free.sort_unstable();
+
+        let mut run: Vec<(usize, usize)> = Vec::new();
+        let mut run_pages = 0;
+        for (idx, order) in free {
+            let contiguous = run
+                .last()
+                .is_some_and(|&(last_idx, last_order)| last_idx + (1usize << last_order) == idx);
+            if !contiguous {
+                run.clear();
+                run_pages = 0;
+            }
+            run.push((idx, order));
+            run_pages += 1usize << order;
+            if run_pages >= num_pages {
+                return Some(run);
+            }
+        }
+        None
+    }
+
+    /// Free the block starting at page `idx`, merging it with its buddies.
+    /// Returns the order of the freed block.
+    fn dealloc_block(&mut self, mut idx: usize) -> Option<usize> {
+        let order = self.alloc_map.remove(&idx)?;
+        self.used_pages -= 1usize << order;
+        let mut cur_order = order;
+        loop {
+            let buddy_idx = idx ^ (1usize << cur_order);
+            if self.remove_free_exact(cur_order, buddy_idx) {
+                idx = cmp::min(idx, buddy_idx);
+                cur_order += 1;
+                if cur_order > self.max_order { break; }
+                continue;
+            } else { break; }
+        }
+        self.push_free(cur_order, idx);
+        Some(order)
+    }
+
+    fn push_free(&mut self, order: usize, idx: usize) {
+        if order >= self.free_lists.len() {
+            self.free_lists.resize(order + 1, BTreeSet::new());
+        }
+        self.free_lists[order].insert(idx);
+    }
+
+    fn pop_free(&mut self, order: usize) -> Option<usize> {
+        self.free_lists.get_mut(order)?.pop_first()
+    }
+
+    fn remove_free_exact(&mut self, order: usize, idx: usize) -> bool {
+        self.free_lists.get_mut(order).is_some_and(|list| list.remove(&idx))
     }
 }
 
@@ -86,31 +251,27 @@ impl PageAllocator for BuddyAllocator {
         let mut mo = 0usize;
         while (1usize << (mo + 1)) <= total_pages { mo += 1; }
 
-        {
-            let mut lists = self.free_lists.lock();
-            lists.clear();
-            lists.resize(mo + 1, Vec::new());
-        }
-        self.alloc_map.lock().clear();
-        *self.used_pages.lock() = 0;
-
+        let mut new_lists = Vec::new();
+        new_lists.resize(mo + 1, BTreeSet::new());
         let mut remaining = total_pages;
         let mut offset = 0usize;
         while remaining > 0 {
             let order = (usize::BITS as usize - 1) - (remaining.leading_zeros() as usize);
             let block_size = 1usize << order;
-            self.push_free(order, offset);
+            new_lists[order].insert(offset);
             offset += block_size;
             remaining -= block_size;
         }
 
-        unsafe {
-            let s = self as *const Self as *mut Self;
-            (*s).base = start;
-            (*s).total_pages = total_pages;
-            (*s).max_order = mo;
-        }
-
+        *self.inner.lock() = BuddyInner {
+            base: start,
+            total_pages,
+            max_order: mo,
+            free_lists: new_lists,
+            alloc_map: BTreeMap::new(),
+            runs: BTreeMap::new(),
+            used_pages: 0,
+        };
         Ok(())
     }
 
@@ -118,82 +279,104 @@ impl PageAllocator for BuddyAllocator {
         if num_pages == 0 { return Err(AllocError::InvalidParam); }
         if align_pow2 < PAGE_SIZE || !align_pow2.is_power_of_two() { return Err(AllocError::InvalidParam); }
 
-        let needed = num_pages.next_power_of_two();
-        let order = ceil_log2(needed);
-        let mut o = order;
-        while o <= self.max_order {
-            if let Some(idx) = self.pop_free(o) {
-                let cur_idx = idx;
-                let mut cur_order = o;
-                while cur_order > order {
-                    cur_order -= 1;
-                    let buddy_idx = cur_idx + (1usize << cur_order);
-                    self.push_free(cur_order, buddy_idx);
-                }
-                self.alloc_map.lock().insert(cur_idx, order);
-                *self.used_pages.lock() += 1usize << order;
-                return Ok(self.base + cur_idx * PAGE_SIZE);
-            }
-            o += 1;
-        }
-        Err(AllocError::NoMemory)
+        let mut inner = self.inner.lock();
+        let idx = inner.alloc(num_pages).ok_or(AllocError::NoMemory)?;
+        Ok(inner.base + idx * PAGE_SIZE)
     }
 
     fn alloc_pages_at(&self, start: usize, num_pages: usize, align_pow2: usize) -> Result<usize, AllocError> {
         if num_pages == 0 { return Err(AllocError::InvalidParam); }
         if align_pow2 < PAGE_SIZE || !align_pow2.is_power_of_two() { return Err(AllocError::InvalidParam); }
-        if start < self.base || start >= self.base + self.total_pages * PAGE_SIZE { return Err(AllocError::InvalidParam); }
+        let mut inner = self.inner.lock();
+        if !inner.contains(start) { return Err(AllocError::InvalidParam); }
         if !is_aligned(start, align_pow2) { return Err(AllocError::InvalidParam); }
-        let idx = (start - self.base) / PAGE_SIZE;
+        let idx = (start - inner.base) / PAGE_SIZE;
         let needed = num_pages.next_power_of_two();
         let order = ceil_log2(needed);
-        if self.remove_free_exact(order, idx) {
-            self.alloc_map.lock().insert(idx, order);
-            *self.used_pages.lock() += 1usize << order;
+        if inner.remove_free_exact(order, idx) {
+            inner.record_alloc(idx, order);
             return Ok(start);
         }
         Err(AllocError::NoMemory)
     }
 
-    fn dealloc_pages(&self, pos: usize, _num_pages: usize) {
-        if pos < self.base || pos >= self.base + self.total_pages * PAGE_SIZE { return; }
-        if !is_aligned(pos, PAGE_SIZE) { return; }
-        let mut idx = (pos - self.base) / PAGE_SIZE;
-        let order = match self.alloc_map.lock().remove(&idx) {
-            Some(o) => o,
-            None => return,
-        };
-        let mut cur_order = order;
-        loop {
-            let buddy_idx = idx ^ (1usize << cur_order);
-            if self.remove_free_exact(cur_order, buddy_idx) {
-                idx = cmp::min(idx, buddy_idx);
-                cur_order += 1;
-                if cur_order > self.max_order { break; }
-                continue;
-            } else { break; }
+    fn dealloc_pages(&self, pos: usize, num_pages: usize) {
+        let mut inner = self.inner.lock();
+        if !inner.contains(pos) {
+            warn!("buddy: dealloc of {:#x} outside the managed region", pos);
+            return;
         }
-        self.push_free(cur_order, idx);
-        *self.used_pages.lock() -= 1usize << order;
-    }
-
-    fn get_stats(&self) -> (f64, usize) {
-        // Calculate fragmentation from free_list snapshot
-        let free_list = self.free_lists.lock();
-        let mut largest_free_block = 0usize;
-        let mut total_free_memory: usize = 0;
-        for v in free_list.iter() {
-            for &b in v.iter() {
-                if b > largest_free_block { largest_free_block = b; }
-                total_free_memory += b;
+        if !is_aligned(pos, PAGE_SIZE) {
+            warn!("buddy: dealloc of unaligned address {:#x}", pos);
+            return;
+        }
+        let idx = (pos - inner.base) / PAGE_SIZE;
+        if let Some((run_idx, run_pages)) = inner.run_covering(idx) {
+            // the blocks of a run are only released together, from its start
+            if run_idx != idx {
+                warn!("buddy: dealloc of {:#x} inside a run of {} pages", pos, run_pages);
+                return;
+            }
+            if num_pages != 0 && num_pages != run_pages {
+                warn!("buddy: dealloc of {} pages at {:#x}, but {} were allocated", num_pages, pos, run_pages);
+                return;
+            }
+            inner.runs.remove(&idx);
+            let mut cur = idx;
+            while cur < idx + run_pages {
+                match inner.dealloc_block(cur) {
+                    Some(order) => cur += 1usize << order,
+                    None => break,
+                }
             }
+            return;
         }
-        let fragmentation = if total_free_memory == 0 {
-            0.0
-        } else {
-            1.0 - (largest_free_block as f64 / total_free_memory as f64)
+        let order = match inner.alloc_map.get(&idx) {
+            Some(&order) => order,
+            None => {
+                warn!("buddy: dealloc of unallocated address {:#x}", pos);
+                return;
+            }
         };
-        (fragmentation, total_free_memory)
+        // requests are rounded up to a power of two, so compare the rounded size
+        if num_pages != 0 && num_pages.next_power_of_two() != 1usize << order {
+            warn!("buddy: dealloc of {} pages at {:#x}, but {} were allocated", num_pages, pos, 1usize << order);
+            return;
+        }
+        inner.dealloc_block(idx);
     }
-}
 
+    fn realloc_pages(&self, pos: usize, old_pages: usize, new_pages: usize, align_pow2: usize) -> Result<usize, AllocError> {
+        if new_pages == 0 { return Err(AllocError::InvalidParam); }
+        if align_pow2 < PAGE_SIZE || !align_pow2.is_power_of_two() { return Err(AllocError::InvalidParam); }
+        let mut inner = self.inner.lock();
+        if !inner.contains(pos) { return Err(AllocError::InvalidParam); }
+        if !is_aligned(pos, PAGE_SIZE) { return Err(AllocError::InvalidParam); }
+        let idx = (pos - inner.base) / PAGE_SIZE;
+        if let Some((run_idx, run_pages)) = inner.run_covering(idx) {
+            // runs assembled from several blocks are never resized in place
+            if run_idx != idx || (old_pages != 0 && old_pages != run_pages) { return Err(AllocError::InvalidParam); }
+        } else {
+            match inner.alloc_map.get(&idx) {
+                Some(&order) if old_pages == 0 || old_pages.next_power_of_two() == 1usize << order => {}
+                _ => return Err(AllocError::InvalidParam),
+            }
+            if is_aligned(pos, align_pow2) && inner.resize_in_place(idx, new_pages) {
+                return Ok(pos);
+            }
+        }
+        let idx = inner.alloc(new_pages).ok_or(AllocError::NoMemory)?;
+        Ok(inner.base + idx * PAGE_SIZE)
+    }
+
+    fn stats(&self) -> AllocStats {
+        let inner = self.inner.lock();
+        // free lists hold block start indices, the block size is given by the order
+        let largest_free_block = inner
+            .free_lists
+            .iter()
+            .rposition(|list| !list.is_empty())
+            .map_or(0, |order| 1usize << order);
+        AllocStats::new(inner.total_pages, inner.used_pages, largest_free_block)
+    }
+}
@@ -1,6 +1,8 @@
 //! Hybrid allocator combining free-lists (for large blocks) and bitmap (for small blocks).
 //!
 //! Strategy:
+//! - The managed pages are split into two disjoint regions: the lower half is
+//!   covered by a bitmap, the upper half by a free-list.
 //! - Blocks >= `THRESHOLD_PAGES` (e.g., 64 pages) are managed by free-list (buddy-like merging).
 //! - Blocks < `THRESHOLD_PAGES` are managed by bitmap for fine-grained allocation.
 //! - This reduces fragmentation for small allocations while keeping large allocations efficient.
@@ -10,7 +12,7 @@ use alloc::vec::Vec;
 use allocator::AllocError;
 use kspin::SpinNoIrq;
 use memory_addr::is_aligned;
-use super::PageAllocator;
+use super::{AllocStats, PageAllocator};
 
 const PAGE_SIZE: usize = 4096;
 const THRESHOLD_PAGES: usize = 64; // Blocks >= 64 pages use free-list; smaller use bitmap
@@ -21,82 +23,279 @@ struct FreeBlockInfo {
     size: usize, // in pages
 }
 
+/// Occupancy of the bitmap region, which serves allocations smaller than
+/// `THRESHOLD_PAGES`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SmallStats {
+    pub total_pages: usize,
+    pub used_pages: usize,
+    pub free_pages: usize,
+}
+
+/// Occupancy of the free-list region, which serves allocations of
+/// `THRESHOLD_PAGES` or more.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LargeStats {
+    pub total_pages: usize,
+    pub used_pages: usize,
+    pub free_pages: usize,
+    pub free_blocks: usize,
+    pub largest_free_block: usize,
+}
+
 pub struct HybridAllocator {
+    /// All the state is behind one lock, so `init` can never run between the
+    /// steps of another operation.
+    inner: SpinNoIrq<HybridInner>,
+}
+
+struct HybridInner {
     base: usize,
     total_pages: usize,
+    /// Pages `0..small_pages` belong to the bitmap, the rest to the free-list.
+    small_pages: usize,
     
     /// Bitmap for small allocations: 1 bit per page, 1 = free, 0 = allocated.
-    bitmap: SpinNoIrq<Vec<u8>>,
+    bitmap: Vec<u8>,
     
     /// Free-list for large blocks: page_index -> block_size (in pages).
-    free_list: SpinNoIrq<BTreeMap<usize, FreeBlockInfo>>,
+    free_list: BTreeMap<usize, FreeBlockInfo>,
     
     /// Track allocations: start_index -> (size_in_pages, is_large).
-    alloc_map: SpinNoIrq<BTreeMap<usize, (usize, bool)>>,
+    alloc_map: BTreeMap<usize, (usize, bool)>,
     
-    used_pages: SpinNoIrq<usize>,
+    used_pages: usize,
 }
 
 impl HybridAllocator {
     pub fn new() -> Self {
         Self {
-            base: 0,
-            total_pages: 0,
-            bitmap: SpinNoIrq::new(Vec::new()),
-            free_list: SpinNoIrq::new(BTreeMap::new()),
-            alloc_map: SpinNoIrq::new(BTreeMap::new()),
-            used_pages: SpinNoIrq::new(0),
+            inner: SpinNoIrq::new(HybridInner {
+                base: 0,
+                total_pages: 0,
+                small_pages: 0,
+                bitmap: Vec::new(),
+                free_list: BTreeMap::new(),
+                alloc_map: BTreeMap::new(),
+                used_pages: 0,
+            }),
         }
     }
 
+    /// Report free/used pages of the bitmap and free-list regions separately,
+    /// so a failed allocation can be attributed to the exhausted region.
+    pub fn region_stats(&self) -> (SmallStats, LargeStats) {
+        let inner = self.inner.lock();
+        let mut small_used = 0usize;
+        let mut large_used = 0usize;
+        for &(size, is_large) in inner.alloc_map.values() {
+            if is_large {
+                large_used += size;
+            } else {
+                small_used += size;
+            }
+        }
+
+        let small_free = inner
+            .bitmap
+            .iter()
+            .map(|byte| byte.count_ones() as usize)
+            .sum();
+
+        let large_free = inner.free_list.values().map(|info| info.size).sum();
+        let largest_free_block = inner.free_list.values().map(|info| info.size).max().unwrap_or(0);
+
+        let small = SmallStats {
+            total_pages: inner.small_pages,
+            used_pages: small_used,
+            free_pages: small_free,
+        };
+        let large = LargeStats {
+            total_pages: inner.total_pages - inner.small_pages,
+            used_pages: large_used,
+            free_pages: large_free,
+            free_blocks: inner.free_list.len(),
+            largest_free_block,
+        };
+        (small, large)
+    }
+}
+
+impl HybridInner {
+    fn contains(&self, pos: usize) -> bool {
+        pos >= self.base && pos < self.base + self.total_pages * PAGE_SIZE
+    }
+
+    /// Record an allocation, keeping `alloc_map` and `used_pages` in sync.
+    fn record_alloc(&mut self, idx: usize, num_pages: usize, is_large: bool) {
+        self.alloc_map.insert(idx, (num_pages, is_large));
+        self.used_pages += num_pages;
+    }
+
+    /// Allocate `num_pages` pages from the region matching their size.
+    /// Returns the start index.
+    fn alloc(&mut self, num_pages: usize) -> Option<usize> {
+        // Determine if we use free-list (large) or bitmap (small)
+        if num_pages >= THRESHOLD_PAGES {
+            // Large allocation: use free-list
+            let (block_idx, block_size) = self.find_free_block(num_pages)?;
+            self.free_list.remove(&block_idx);
+
+            // Split if needed
+            self.split_block(block_idx, block_size, num_pages);
+
+            // Record allocation
+            self.record_alloc(block_idx, num_pages, true);
+            Some(block_idx)
+        } else {
+            // Small allocation: use bitmap
+            let block_idx = self.find_free_in_bitmap(num_pages)?;
+            self.mark_allocated(block_idx, num_pages);
+            self.record_alloc(block_idx, num_pages, false);
+            Some(block_idx)
+        }
+    }
+
+    /// Resize the allocation at page `idx` to `new_pages` without moving it.
+    /// Small allocations grow into free bitmap pages right after them, large
+    /// ones into the adjacent free-list block. An allocation never moves
+    /// between the bitmap and the free-list. Returns `false` if it cannot be
+    /// resized in place.
+    fn resize_in_place(&mut self, idx: usize, new_pages: usize) -> bool {
+        let (size, is_large) = match self.alloc_map.get(&idx) {
+            Some(&info) => info,
+            None => return false,
+        };
+        if is_large != (new_pages >= THRESHOLD_PAGES) {
+            return false;
+        }
+        let end_idx = idx + size;
+        let new_end_idx = idx + new_pages;
+
+        if new_pages > size {
+            let region_end = if is_large {
+                self.total_pages
+            } else {
+                self.small_pages
+            };
+            if new_end_idx > region_end {
+                return false;
+            }
+            if is_large {
+                let next_size = match self.free_list.get(&end_idx) {
+                    Some(info) if end_idx + info.size >= new_end_idx => info.size,
+                    _ => return false,
+                };
+                self.free_list.remove(&end_idx);
+                if end_idx + next_size > new_end_idx {
+                    self.free_list.insert(new_end_idx, FreeBlockInfo {
+                        size: end_idx + next_size - new_end_idx,
+                    });
+                }
+            } else {
+                if !(end_idx..new_end_idx).all(|i| self.bitmap[i / 8] & (1u8 << (i % 8)) != 0) {
+                    return false;
+                }
+                self.mark_allocated(end_idx, new_pages - size);
+            }
+        } else if new_pages < size {
+            if is_large {
+                // give back the tail, merged with the block right after it
+                let mut tail_size = size - new_pages;
+                if let Some(info) = self.free_list.remove(&end_idx) {
+                    tail_size += info.size;
+                }
+                self.free_list.insert(new_end_idx, FreeBlockInfo { size: tail_size });
+            } else {
+                self.mark_free(new_end_idx, size - new_pages);
+            }
+        }
+
+        self.alloc_map.insert(idx, (new_pages, is_large));
+        self.used_pages = self.used_pages + new_pages - size;
+        true
+    }
+
     /// Mark pages in bitmap as free (bit = 1).
-    fn mark_free(&self, start_idx: usize, count: usize) {
-        let mut bitmap = self.bitmap.lock();
+    fn mark_free(&mut self, start_idx: usize, count: usize) {
         for i in start_idx..start_idx + count {
-            if i < self.total_pages {
+            if i < self.small_pages {
                 let byte_idx = i / 8;
                 let bit_idx = i % 8;
-                bitmap[byte_idx] |= 1u8 << bit_idx;
+                self.bitmap[byte_idx] |= 1u8 << bit_idx;
             }
         }
     }
 
     /// Mark pages in bitmap as allocated (bit = 0).
-    fn mark_allocated(&self, start_idx: usize, count: usize) {
-        let mut bitmap = self.bitmap.lock();
+    fn mark_allocated(&mut self, start_idx: usize, count: usize) {
         for i in start_idx..start_idx + count {
-            if i < self.total_pages {
+            if i < self.small_pages {
                 let byte_idx = i / 8;
                 let bit_idx = i % 8;
-                bitmap[byte_idx] &= !(1u8 << bit_idx);
+                self.bitmap[byte_idx] &= !(1u8 << bit_idx);
             }
         }
     }
 
-    /// Find first free bit in bitmap.
+    /// Find the first run of `needed` free pages in bitmap.
+    ///
+    /// Scans once while tracking the current run length. Fully free or fully
+    /// allocated bytes are handled as a whole, so only partially allocated
+    /// bytes are inspected bit by bit. The padding bits of the last byte are
+    /// always 0, so a run never extends past `total_pages`.
     fn find_free_in_bitmap(&self, needed: usize) -> Option<usize> {
-        let bitmap = self.bitmap.lock();
-        for start in 0..self.total_pages - needed + 1 {
-            let mut all_free = true;
-            for i in start..start + needed {
-                let byte_idx = i / 8;
-                let bit_idx = i % 8;
-                if (bitmap[byte_idx] & (1u8 << bit_idx)) == 0 {
-                    all_free = false;
-                    break;
+        let mut run_start = 0;
+        let mut run_len = 0;
+        for (byte_idx, &byte) in self.bitmap.iter().enumerate() {
+            match byte {
+                0x00 => run_len = 0,
+                0xFF => {
+                    if run_len == 0 {
+                        run_start = byte_idx * 8;
+                    }
+                    run_len += 8;
+                    if run_len >= needed {
+                        return Some(run_start);
+                    }
+                }
+                _ => {
+                    for bit_idx in 0..8 {
+                        if byte & (1u8 << bit_idx) == 0 {
+                            run_len = 0;
+                            continue;
+                        }
+                        if run_len == 0 {
+                            run_start = byte_idx * 8 + bit_idx;
+                        }
+                        run_len += 1;
+                        if run_len >= needed {
+                            return Some(run_start);
+                        }
+                    }
                 }
-            }
-            if all_free {
-                return Some(start);
             }
         }
         None
     }
 
+    /// Length of the longest run of free pages in bitmap.
+    fn longest_free_run_in_bitmap(&self) -> usize {
+        let (mut longest, mut run_len) = (0, 0);
+        for i in 0..self.bitmap.len() * 8 {
+            if self.bitmap[i / 8] & (1u8 << (i % 8)) != 0 {
+                run_len += 1;
+                longest = longest.max(run_len);
+            } else {
+                run_len = 0;
+            }
+        }
+        longest
+    }
+
     /// Find first free block in free-list that fits the requested size.
     fn find_free_block(&self, needed_pages: usize) -> Option<(usize, usize)> {
-        let free_list = self.free_list.lock();
-        for (&idx, info) in free_list.iter() {
+        for (&idx, info) in self.free_list.iter() {
             if info.size >= needed_pages {
                 return Some((idx, info.size));
             }
@@ -105,19 +304,19 @@ impl HybridAllocator {
     }
 
     /// Split a large block if it's larger than needed.
-    fn split_block(&self, start_idx: usize, original_size: usize, needed_size: usize) {
+    fn split_block(&mut self, start_idx: usize, original_size: usize, needed_size: usize) {
         if original_size > needed_size {
             let remaining_start = start_idx + needed_size;
             let remaining_size = original_size - needed_size;
-            self.free_list.lock().insert(remaining_start, FreeBlockInfo {
+            self.free_list.insert(remaining_start, FreeBlockInfo {
                 size: remaining_size,
             });
         }
     }
 
     /// Try to merge adjacent free blocks.
-    fn try_merge(&self, start_idx: usize, size: usize) {
-        let mut free_list = self.free_list.lock();
+    fn try_merge(&mut self, start_idx: usize, size: usize) {
+        let free_list = &mut self.free_list;
         let end_idx = start_idx + size;
 
         // Try merging with block before
@@ -161,40 +360,38 @@ impl PageAllocator for HybridAllocator {
             return Err(AllocError::InvalidParam);
         }
 
+        // The lower half of the pages is left to the bitmap
+        let small_pages = total_pages / 2;
+
         // Initialize bitmap: all pages are free (bit = 1)
-        let bitmap_size = (total_pages + 7) / 8;
+        let bitmap_size = (small_pages + 7) / 8;
         let bitmap = {
             let mut vec = Vec::new();
             vec.resize(bitmap_size, 0xFFu8);
             vec
         };
         let mut bitmap = bitmap;
-        if total_pages % 8 != 0 {
+        if small_pages % 8 != 0 {
             let last_byte_idx = bitmap_size - 1;
-            let unused_bits = 8 - (total_pages % 8);
+            let unused_bits = 8 - (small_pages % 8);
             bitmap[last_byte_idx] &= 0xFFu8 >> unused_bits;
         }
 
-        // All memory starts as one large free block
+        // The upper half starts as one large free block
         let mut free_list = BTreeMap::new();
-        free_list.insert(0, FreeBlockInfo { size: total_pages });
-
-        let mut bitmap_guard = self.bitmap.lock();
-        *bitmap_guard = bitmap;
-        drop(bitmap_guard);
-
-        let mut fl = self.free_list.lock();
-        *fl = free_list;
-        drop(fl);
-
-        self.alloc_map.lock().clear();
-        *self.used_pages.lock() = 0;
-
-        unsafe {
-            let s = self as *const Self as *mut Self;
-            (*s).base = start;
-            (*s).total_pages = total_pages;
-        }
+        free_list.insert(small_pages, FreeBlockInfo {
+            size: total_pages - small_pages,
+        });
+
+        *self.inner.lock() = HybridInner {
+            base: start,
+            total_pages,
+            small_pages,
+            bitmap,
+            free_list,
+            alloc_map: BTreeMap::new(),
+            used_pages: 0,
+        };
 
         Ok(())
     }
@@ -207,35 +404,9 @@ impl PageAllocator for HybridAllocator {
             return Err(AllocError::InvalidParam);
         }
 
-        // Determine if we use free-list (large) or bitmap (small)
-        if num_pages >= THRESHOLD_PAGES {
-            // Large allocation: use free-list
-            if let Some((block_idx, block_size)) = self.find_free_block(num_pages) {
-                let mut free_list = self.free_list.lock();
-                free_list.remove(&block_idx);
-                drop(free_list);
-
-                // Split if needed
-                self.split_block(block_idx, block_size, num_pages);
-
-                // Record allocation
-                self.alloc_map.lock().insert(block_idx, (num_pages, true));
-                *self.used_pages.lock() += num_pages;
-
-                return Ok(self.base + block_idx * PAGE_SIZE);
-            }
-        } else {
-            // Small allocation: use bitmap
-            if let Some(block_idx) = self.find_free_in_bitmap(num_pages) {
-                self.mark_allocated(block_idx, num_pages);
-                self.alloc_map.lock().insert(block_idx, (num_pages, false));
-                *self.used_pages.lock() += num_pages;
-
-                return Ok(self.base + block_idx * PAGE_SIZE);
-            }
-        }
-
-        Err(AllocError::NoMemory)
+        let mut inner = self.inner.lock();
+        let block_idx = inner.alloc(num_pages).ok_or(AllocError::NoMemory)?;
+        Ok(inner.base + block_idx * PAGE_SIZE)
     }
 
     fn alloc_pages_at(
@@ -250,50 +421,45 @@ impl PageAllocator for HybridAllocator {
         if align_pow2 < PAGE_SIZE || !align_pow2.is_power_of_two() {
             return Err(AllocError::InvalidParam);
         }
-        if start < self.base || start >= self.base + self.total_pages * PAGE_SIZE {
+        let mut inner = self.inner.lock();
+        if !inner.contains(start) {
             return Err(AllocError::InvalidParam);
         }
         if !is_aligned(start, align_pow2) {
             return Err(AllocError::InvalidParam);
         }
 
-        let idx = (start - self.base) / PAGE_SIZE;
+        let idx = (start - inner.base) / PAGE_SIZE;
 
         // Try to allocate at the exact location
         if num_pages >= THRESHOLD_PAGES {
             // Large: check free-list
-            let mut free_list = self.free_list.lock();
-            if let Some(info) = free_list.get(&idx) {
+            if let Some(info) = inner.free_list.get(&idx) {
                 if info.size >= num_pages {
                     let size = info.size;
-                    free_list.remove(&idx);
-                    drop(free_list);
+                    inner.free_list.remove(&idx);
 
-                    self.split_block(idx, size, num_pages);
-                    self.alloc_map.lock().insert(idx, (num_pages, true));
-                    *self.used_pages.lock() += num_pages;
+                    inner.split_block(idx, size, num_pages);
+                    inner.record_alloc(idx, num_pages, true);
 
                     return Ok(start);
                 }
             }
-        } else {
+        } else if idx + num_pages <= inner.small_pages {
             // Small: check bitmap
-            let bitmap = self.bitmap.lock();
             let mut all_free = true;
             for i in idx..idx + num_pages {
                 let byte_idx = i / 8;
                 let bit_idx = i % 8;
-                if (bitmap[byte_idx] & (1u8 << bit_idx)) == 0 {
+                if (inner.bitmap[byte_idx] & (1u8 << bit_idx)) == 0 {
                     all_free = false;
                     break;
                 }
             }
-            drop(bitmap);
 
             if all_free {
-                self.mark_allocated(idx, num_pages);
-                self.alloc_map.lock().insert(idx, (num_pages, false));
-                *self.used_pages.lock() += num_pages;
+                inner.mark_allocated(idx, num_pages);
+                inner.record_alloc(idx, num_pages, false);
 
                 return Ok(start);
             }
@@ -302,56 +468,86 @@ impl PageAllocator for HybridAllocator {
         Err(AllocError::NoMemory)
     }
 
-    fn dealloc_pages(&self, pos: usize, _num_pages: usize) {
-        if pos < self.base || pos >= self.base + self.total_pages * PAGE_SIZE {
+    fn dealloc_pages(&self, pos: usize, num_pages: usize) {
+        let mut inner = self.inner.lock();
+        if !inner.contains(pos) {
+            warn!("hybrid: dealloc of {:#x} outside the managed region", pos);
             return;
         }
         if !is_aligned(pos, PAGE_SIZE) {
+            warn!("hybrid: dealloc of unaligned address {:#x}", pos);
             return;
         }
 
-        let idx = (pos - self.base) / PAGE_SIZE;
+        let idx = (pos - inner.base) / PAGE_SIZE;
 
         // Look up the allocation
-        let alloc_info = match self.alloc_map.lock().remove(&idx) {
-            Some(info) => info,
-            None => return,
+        let (size, is_large) = match inner.alloc_map.get(&idx) {
+            Some(&info) => info,
+            None => {
+                warn!("hybrid: dealloc of unallocated address {:#x}", pos);
+                return;
+            }
         };
-
-        let (size, is_large) = alloc_info;
+        if num_pages != 0 && num_pages != size {
+            warn!("hybrid: dealloc of {} pages at {:#x}, but {} were allocated", num_pages, pos, size);
+            return;
+        }
+        inner.alloc_map.remove(&idx);
+        inner.used_pages -= size;
 
         if is_large {
             // Return to free-list and try to merge
-            self.free_list.lock().insert(idx, FreeBlockInfo { size });
-            self.try_merge(idx, size);
+            inner.free_list.insert(idx, FreeBlockInfo { size });
+            inner.try_merge(idx, size);
         } else {
             // Return to bitmap
-            self.mark_free(idx, size);
+            inner.mark_free(idx, size);
         }
-
-        *self.used_pages.lock() -= size;
     }
 
-    fn get_stats(&self) -> (f64, usize) {
-        // Simple fragmentation estimate for hybrid allocator
-        // We'll compute based on the free-list large blocks
-        let free_list = self.free_list.lock();
-        let mut largest_free = 0usize;
-        let mut total_free = 0usize;
-
-        for info in free_list.values() {
-            total_free += info.size;
-            if info.size > largest_free {
-                largest_free = info.size;
-            }
+    fn realloc_pages(
+        &self,
+        pos: usize,
+        old_pages: usize,
+        new_pages: usize,
+        align_pow2: usize,
+    ) -> Result<usize, AllocError> {
+        if new_pages == 0 {
+            return Err(AllocError::InvalidParam);
+        }
+        if align_pow2 < PAGE_SIZE || !align_pow2.is_power_of_two() {
+            return Err(AllocError::InvalidParam);
+        }
+        let mut inner = self.inner.lock();
+        if !inner.contains(pos) {
+            return Err(AllocError::InvalidParam);
+        }
+        if !is_aligned(pos, PAGE_SIZE) {
+            return Err(AllocError::InvalidParam);
         }
 
-        let fragmentation = if total_free == 0 {
-            0.0
-        } else {
-            1.0 - (largest_free as f64 / total_free as f64)
-        };
-        
-        (fragmentation, total_free)
+        let idx = (pos - inner.base) / PAGE_SIZE;
+        match inner.alloc_map.get(&idx) {
+            Some(&(size, _)) if old_pages == 0 || old_pages == size => {}
+            _ => return Err(AllocError::InvalidParam),
+        }
+        if is_aligned(pos, align_pow2) && inner.resize_in_place(idx, new_pages) {
+            return Ok(pos);
+        }
+        let block_idx = inner.alloc(new_pages).ok_or(AllocError::NoMemory)?;
+        Ok(inner.base + block_idx * PAGE_SIZE)
+    }
+
+    fn stats(&self) -> AllocStats {
+        let inner = self.inner.lock();
+        let largest_free_block = inner
+            .free_list
+            .values()
+            .map(|info| info.size)
+            .max()
+            .unwrap_or(0)
+            .max(inner.longest_free_run_in_bitmap());
+        AllocStats::new(inner.total_pages, inner.used_pages, largest_free_block)
     }
 }
@@ -6,6 +6,39 @@
 
 use allocator::AllocError;
 
+/// Page usage snapshot of a [`PageAllocator`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AllocStats {
+    pub total_pages: usize,
+    pub used_pages: usize,
+    pub free_pages: usize,
+    /// Size (in pages) of the largest contiguous free block.
+    pub largest_free_block: usize,
+    /// `1 - largest_free_block / free_pages`, or 0 if nothing is free.
+    pub fragmentation: f64,
+}
+
+impl AllocStats {
+    /// Build the stats from page counts, deriving `free_pages` and
+    /// `fragmentation`.
+    pub fn new(total_pages: usize, used_pages: usize, largest_free_block: usize) -> Self {
+        let free_pages = total_pages.saturating_sub(used_pages);
+        let largest_free_block = largest_free_block.min(free_pages);
+        let fragmentation = if free_pages == 0 {
+            0.0
+        } else {
+            1.0 - (largest_free_block as f64 / free_pages as f64)
+        };
+        Self {
+            total_pages,
+            used_pages,
+            free_pages,
+            largest_free_block,
+            fragmentation,
+        }
+    }
+}
+
 /// Minimal allocator trait for page-level operations used by the runtime
 /// switching infrastructure.
 pub trait PageAllocator: Send + Sync {
@@ -27,18 +60,41 @@ pub trait PageAllocator: Send + Sync {
     ) -> Result<usize, AllocError>;
 
     /// Deallocate contiguous pages starting from `pos`.
+    ///
+    /// `pos` must be the start of a live allocation. If `num_pages` is
+    /// nonzero it must match the allocated size, otherwise nothing is freed
+    /// and a warning is logged. Pass 0 to free whatever was allocated at `pos`.
     fn dealloc_pages(&self, pos: usize, num_pages: usize);
 
-    /// Optional: Return diagnostics (fragmentation and free memory).
-    /// Default implementation returns (0.0, 0) indicating no diagnostic data available.
-    fn get_stats(&self) -> (f64, usize) {
-        (0.0, 0)
+    /// Resize the allocation of `old_pages` pages at `pos` to `new_pages`.
+    ///
+    /// It first tries to grow or shrink the allocation in place and returns
+    /// `pos` on success. Otherwise it allocates `new_pages` elsewhere and
+    /// returns the new address. The allocator never touches page contents:
+    /// after a relocation the old pages stay allocated, and the caller is
+    /// responsible for copying the data and then freeing them with
+    /// `dealloc_pages(pos, old_pages)`.
+    ///
+    /// The default implementation always relocates.
+    fn realloc_pages(
+        &self,
+        pos: usize,
+        old_pages: usize,
+        new_pages: usize,
+        align_pow2: usize,
+    ) -> Result<usize, AllocError> {
+        let _ = (pos, old_pages);
+        self.alloc_pages(new_pages, align_pow2)
     }
+
+    /// Return page usage and fragmentation of the managed region.
+    fn stats(&self) -> AllocStats;
 }
 
-#[cfg(feature = "buddy")]
+// the runtime module falls back to the buddy allocator
+#[cfg(any(feature = "buddy", feature = "runtime-switch"))]
 mod buddy;
-#[cfg(feature = "buddy")]
+#[cfg(any(feature = "buddy", feature = "runtime-switch"))]
 pub use buddy::BuddyAllocator;
 
 #[cfg(feature = "bitmap")]
@@ -49,15 +105,17 @@ pub use bitmap::BitmapAllocator;
 #[cfg(feature = "hybrid")]
 mod hybrid;
 #[cfg(feature = "hybrid")]
-pub use hybrid::HybridAllocator;
+pub use hybrid::{HybridAllocator, LargeStats, SmallStats};
 
 // When runtime switching is enabled, compile helpers to build dynamic dispatch
-// pointers. The full runtime switcher will be implemented in following steps.
+// pointers and route page allocations through the selected allocator.
 #[cfg(feature = "runtime-switch")]
 pub mod runtime {
-    use super::PageAllocator;
+    use super::{BuddyAllocator, PageAllocator};
+    use alloc::boxed::Box;
     use allocator::AllocError;
     use core::option::Option;
+    use core::sync::atomic::{AtomicBool, Ordering};
     use kspin::SpinNoIrq;
 
     // Global storage for the runtime-selected page allocator. When `None`,
@@ -65,44 +123,101 @@ pub mod runtime {
     static GLOBAL_PAGE_ALLOC: SpinNoIrq<Option<Box<dyn PageAllocator>>> =
         SpinNoIrq::new(None);
 
-    /// Try to set the global runtime allocator. Overwrites any previous value.
-    pub fn set_runtime_allocator(a: Box<dyn PageAllocator>) {
+    // The built-in fallback. It is a static rather than a lazily boxed
+    // allocator, so serving a request never has to create it.
+    static FALLBACK: BuddyAllocator = BuddyAllocator::new();
+    static INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+    /// Give the built-in fallback allocator the memory region it manages.
+    ///
+    /// The allocators keep their metadata on the heap, so this must be called
+    /// once the byte allocator is up, and only once.
+    pub fn init(start_vaddr: usize, size: usize) -> Result<(), AllocError> {
+        FALLBACK.init(start_vaddr, size)?;
+        INITIALIZED.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// Whether page allocations should go through this module, i.e. a
+    /// runtime allocator is set or the fallback has a region.
+    pub fn is_enabled() -> bool {
+        INITIALIZED.load(Ordering::Acquire) || GLOBAL_PAGE_ALLOC.lock().is_some()
+    }
+
+    /// Run `f` on the runtime allocator, or on the built-in fallback if none
+    /// is set.
+    ///
+    /// The allocators may grow the heap while the lock is held. This cannot
+    /// deadlock because the heap takes its pages from the page allocator of
+    /// [`GlobalAllocator`](crate::GlobalAllocator), never from this module.
+    fn with_allocator<T>(
+        f: impl FnOnce(&dyn PageAllocator) -> Result<T, AllocError>,
+    ) -> Result<T, AllocError> {
+        let slot = GLOBAL_PAGE_ALLOC.lock();
+        f(slot.as_deref().unwrap_or(&FALLBACK))
+    }
+
+    /// Fail with `MemoryOverlap` if the allocator in `slot` (or the fallback)
+    /// still has pages handed out.
+    fn check_idle(slot: &Option<Box<dyn PageAllocator>>) -> Result<(), AllocError> {
+        let current = slot.as_deref().unwrap_or(&FALLBACK);
+        if current.stats().used_pages != 0 {
+            return Err(AllocError::MemoryOverlap);
+        }
+        Ok(())
+    }
+
+    /// Set the global runtime allocator, replacing any previous one.
+    ///
+    /// Pages must be freed through the allocator that handed them out, and
+    /// the replacement usually manages the same region. So the switch is
+    /// rejected with `MemoryOverlap` while the allocator currently serving
+    /// requests has pages allocated.
+    pub fn set_runtime_allocator(a: Box<dyn PageAllocator>) -> Result<(), AllocError> {
         let mut slot = GLOBAL_PAGE_ALLOC.lock();
+        check_idle(&slot)?;
         *slot = Some(a);
+        Ok(())
     }
 
     /// Clear the runtime allocator (revert to built-in fallback).
-    pub fn clear_runtime_allocator() {
+    ///
+    /// Like [`set_runtime_allocator`], it fails with `MemoryOverlap` while
+    /// the runtime allocator has pages allocated.
+    pub fn clear_runtime_allocator() -> Result<(), AllocError> {
         let mut slot = GLOBAL_PAGE_ALLOC.lock();
+        check_idle(&slot)?;
         *slot = None;
+        Ok(())
     }
 
-    /// Allocate pages via the runtime allocator if present.
+    /// Name of the allocator currently serving requests: the runtime one if
+    /// set, otherwise the built-in fallback.
+    pub fn current_allocator_name() -> &'static str {
+        GLOBAL_PAGE_ALLOC
+            .lock()
+            .as_deref()
+            .unwrap_or(&FALLBACK)
+            .name()
+    }
+
+    /// Allocate pages via the runtime allocator, or the fallback if not set.
     pub fn alloc_pages(num_pages: usize, align_pow2: usize) -> Result<usize, AllocError> {
-        let slot = GLOBAL_PAGE_ALLOC.lock();
-        if let Some(ref a) = *slot {
-            a.alloc_pages(num_pages, align_pow2)
-        } else {
-            Err(AllocError::NoMemory)
-        }
+        with_allocator(|a| a.alloc_pages(num_pages, align_pow2))
     }
 
-    /// Allocate pages at exact location via runtime allocator if present.
+    /// Allocate pages at exact location via the runtime allocator, or the
+    /// fallback if not set.
     pub fn alloc_pages_at(start: usize, num_pages: usize, align_pow2: usize) -> Result<usize, AllocError> {
-        let slot = GLOBAL_PAGE_ALLOC.lock();
-        if let Some(ref a) = *slot {
-            a.alloc_pages_at(start, num_pages, align_pow2)
-        } else {
-            Err(AllocError::NoMemory)
-        }
+        with_allocator(|a| a.alloc_pages_at(start, num_pages, align_pow2))
     }
 
-    /// Deallocate pages via the runtime allocator if present.
+    /// Deallocate pages via the runtime allocator, or the fallback if not set.
     pub fn dealloc_pages(pos: usize, num_pages: usize) {
-        let slot = GLOBAL_PAGE_ALLOC.lock();
-        if let Some(ref a) = *slot {
-            a.dealloc_pages(pos, num_pages)
-        }
+        let _ = with_allocator(|a| {
+            a.dealloc_pages(pos, num_pages);
+            Ok(())
+        });
     }
 
     /// Helper to create an allocator by name. Recognized names: "buddy",
@@ -110,16 +225,8 @@ pub mod runtime {
     /// is not compiled-in (feature not enabled) or name is unknown.
     pub fn make_by_name(name: &str) -> Result<Box<dyn PageAllocator>, &'static str> {
         match name {
-            "buddy" => {
-                #[cfg(feature = "buddy")]
-                {
-                    return Ok(Box::new(crate::allocators::BuddyAllocator::new()));
-                }
-                #[cfg(not(feature = "buddy"))]
-                {
-                    return Err("buddy feature not enabled");
-                }
-            }
+            // always compiled in, as the built-in fallback
+            "buddy" => Ok(Box::new(BuddyAllocator::new())),
             "bitmap" => {
                 #[cfg(feature = "bitmap")]
                 {
@@ -65,13 +65,13 @@ impl AllocatorTester {
         let start_dealloc = std::time::Instant::now();
         for &idx in &test_case.deallocation_order {
             if let Some(Some(addr)) = allocated_addrs.get(idx).cloned() {
-                allocator.dealloc_pages(addr, 1);
+                allocator.dealloc_pages(addr, test_case.allocation_sizes[idx]);
             }
         }
         let total_dealloc_time = start_dealloc.elapsed().as_nanos() as u64;
 
         // Get fragmentation and free memory from allocator's diagnostic stats
-        let (fragmentation, total_free_memory) = allocator.get_stats();
+        let stats = allocator.stats();
 
         TestResult {
             total_allocations: test_case.allocation_sizes.len(),
@@ -79,9 +79,9 @@ impl AllocatorTester {
             failed_allocations,
             average_allocation_time_ns: if test_case.allocation_sizes.len() > 0 { total_alloc_time / test_case.allocation_sizes.len() as u64 } else { 0 },
             average_deallocation_time_ns: if test_case.deallocation_order.len() > 0 { total_dealloc_time / test_case.deallocation_order.len() as u64 } else { 0 },
-            fragmentation,
+            fragmentation: stats.fragmentation,
             peak_memory_usage,
-            remaining_free_memory: total_free_memory,
+            remaining_free_memory: stats.free_pages * PAGE_SIZE,
         }
     }
 }
@@ -88,10 +88,12 @@ impl GlobalAllocator {
     pub fn init(&self, start_vaddr: usize, size: usize) {
         assert!(size > MIN_HEAP_SIZE);
         let init_heap_size = MIN_HEAP_SIZE;
-        self.palloc.lock().init(start_vaddr, size);
-        let heap_ptr = self
+        let mut palloc = self.palloc.lock();
+        palloc.init(start_vaddr, size);
+        let heap_ptr = palloc
             .alloc_pages(init_heap_size / PAGE_SIZE, PAGE_SIZE)
             .unwrap();
+        drop(palloc);
         self.balloc.lock().init(heap_ptr, init_heap_size);
     }
 
@@ -120,7 +122,12 @@ impl GlobalAllocator {
                     .max(layout.size())
                     .next_power_of_two()
                     .max(PAGE_SIZE);
-                let heap_ptr = self.alloc_pages(expand_size / PAGE_SIZE, PAGE_SIZE)?;
+                // never through the runtime allocators, which may themselves
+                // allocate on the heap
+                let heap_ptr = self
+                    .palloc
+                    .lock()
+                    .alloc_pages(expand_size / PAGE_SIZE, PAGE_SIZE)?;
                 debug!(
                     "expand heap memory: [{:#x}, {:#x})",
                     heap_ptr,
@@ -150,10 +157,8 @@ impl GlobalAllocator {
     /// aligned to it.
     pub fn alloc_pages(&self, num_pages: usize, align_pow2: usize) -> AllocResult<usize> {
         #[cfg(feature = "runtime-switch")]
-        {
-            if let Ok(v) = crate::allocators::runtime::alloc_pages(num_pages, align_pow2) {
-                return Ok(v);
-            }
+        if crate::allocators::runtime::is_enabled() {
+            return crate::allocators::runtime::alloc_pages(num_pages, align_pow2);
         }
 
         self.palloc.lock().alloc_pages(num_pages, align_pow2)
@@ -173,10 +178,8 @@ impl GlobalAllocator {
         align_pow2: usize,
     ) -> AllocResult<usize> {
         #[cfg(feature = "runtime-switch")]
-        {
-            if let Ok(v) = crate::allocators::runtime::alloc_pages_at(start, num_pages, align_pow2) {
-                return Ok(v);
-            }
+        if crate::allocators::runtime::is_enabled() {
+            return crate::allocators::runtime::alloc_pages_at(start, num_pages, align_pow2);
         }
 
         self.palloc
@@ -193,16 +196,9 @@ impl GlobalAllocator {
     /// [`alloc_pages`]: GlobalAllocator::alloc_pages
     pub fn dealloc_pages(&self, pos: usize, num_pages: usize) {
         #[cfg(feature = "runtime-switch")]
-        {
-            // If runtime allocator is present, let it handle deallocation.
+        if crate::allocators::runtime::is_enabled() {
             crate::allocators::runtime::dealloc_pages(pos, num_pages);
-            // Also fall back to built-in dealloc in case runtime allocator wasn't set.
-            let slot_present = {
-                // We don't have a direct 'is set' accessor; attempt alloc_pages with 0 may not be valid.
-                // Simpler: just call built-in dealloc as well to be conservative.
-                false
-            };
-            let _ = slot_present; // keep compiler happy for now
+            return;
         }
 
         self.palloc.lock().dealloc_pages(pos, num_pages)
@@ -270,6 +266,10 @@ pub fn global_init(start_vaddr: usize, size: usize) {
 
 /// Initializes the global allocator and optionally sets the runtime page
 /// allocator by name (when `runtime-switch` feature is enabled).
+///
+/// The heap keeps growing from the built-in page allocator, while half of
+/// the pages left after the initial heap are reserved for the runtime page
+/// allocators.
 #[cfg(feature = "runtime-switch")]
 pub fn global_init_with_allocator(start_vaddr: usize, size: usize, allocator_name: Option<&str>) {
     debug!(
@@ -277,16 +277,39 @@ pub fn global_init_with_allocator(start_vaddr: usize, size: usize, allocator_nam
         start_vaddr,
         start_vaddr + size
     );
-    // Initialize the built-in global allocator first (fallback)
+    // Bring up the heap first: the runtime allocators keep their metadata on
+    // it, and it keeps growing from the built-in page allocator.
     GLOBAL_ALLOCATOR.init(start_vaddr, size);
 
+    // Hand half of the remaining pages over to the runtime module, so its
+    // allocators never give out pages used by the heap.
+    let rt_size = GLOBAL_ALLOCATOR.available_pages() / 2 * PAGE_SIZE;
+    let res = GLOBAL_ALLOCATOR
+        .palloc
+        .lock()
+        .alloc_pages(rt_size / PAGE_SIZE, PAGE_SIZE);
+    // the page allocator is unlocked by now, initializing may grow the heap
+    let res = res.and_then(|start| crate::allocators::runtime::init(start, rt_size).map(|_| start));
+    let rt_start = match res {
+        Ok(start) => start,
+        Err(e) => {
+            warn!("failed to initialize runtime page allocation: {:?}", e);
+            return;
+        }
+    };
+
     if let Some(name) = allocator_name {
         match crate::allocators::runtime::make_by_name(name) {
-            Ok(mut boxed) => {
-                // Try to initialize runtime allocator with the same memory region.
-                let _ = boxed.init(start_vaddr, size);
-                crate::allocators::runtime::set_runtime_allocator(boxed);
-                info!("runtime page allocator set to: {}", name);
+            Ok(boxed) => {
+                // nothing has been allocated from the fallback yet, so the
+                // new allocator can take over its whole region
+                let res = boxed
+                    .init(rt_start, rt_size)
+                    .and_then(|_| crate::allocators::runtime::set_runtime_allocator(boxed));
+                match res {
+                    Ok(()) => info!("runtime page allocator set to: {}", name),
+                    Err(e) => warn!("failed to select runtime allocator '{}': {:?}", name, e),
+                }
             }
             Err(e) => {
                 warn!("failed to select runtime allocator '{}': {}", name, e);
@@ -62,10 +62,7 @@ impl AllocatorTester {
         total_dealloc_time = start_dealloc.elapsed().as_nanos() as u64;
 
         // Calculate fragmentation
-        let free_list = allocator.free_list_snapshot();
-        let largest_free_block = free_list.iter().flatten().max().unwrap_or(&0);
-        let total_free_memory: usize = free_list.iter().flatten().sum();
-        let fragmentation = 1.0 - (*largest_free_block as f64 / total_free_memory as f64);
+        let stats = allocator.stats();
 
         TestResult {
             total_allocations: test_case.allocation_sizes.len(),
@@ -73,9 +70,9 @@ impl AllocatorTester {
             failed_allocations,
             average_allocation_time_ns: total_alloc_time / test_case.allocation_sizes.len() as u64,
             average_deallocation_time_ns: total_dealloc_time / test_case.deallocation_order.len() as u64,
-            fragmentation,
+            fragmentation: stats.fragmentation,
             peak_memory_usage,
-            remaining_free_memory: total_free_memory,
+            remaining_free_memory: stats.free_pages * 4096,
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,131 @@
+#![cfg(feature = "bitmap")]
+
+use axalloc::allocators::{BitmapAllocator, PageAllocator};
+use allocator::AllocError;
+
+const PAGE_SIZE: usize = 4096;
+const BASE: usize = 0x1000;
+const TOTAL_PAGES: usize = 20;
+
+fn new_allocator() -> BitmapAllocator {
+    let allocator = BitmapAllocator::new();
+    allocator.init(BASE, TOTAL_PAGES * PAGE_SIZE).unwrap();
+    allocator
+}
+
+#[test]
+fn test_alloc_first_fit() {
+    let allocator = new_allocator();
+
+    let a = allocator.alloc_pages(3, PAGE_SIZE).unwrap();
+    let b = allocator.alloc_pages(2, PAGE_SIZE).unwrap();
+    assert_eq!(a, BASE);
+    assert_eq!(b, BASE + 3 * PAGE_SIZE);
+
+    // the hole left by `a` is reused by a request that fits
+    allocator.dealloc_pages(a, 3);
+    assert_eq!(allocator.alloc_pages(2, PAGE_SIZE), Ok(BASE));
+    assert_eq!(allocator.alloc_pages(2, PAGE_SIZE), Ok(BASE + 5 * PAGE_SIZE));
+    assert_eq!(allocator.stats().used_pages, 6);
+}
+
+#[test]
+fn test_alloc_aligned() {
+    let allocator = new_allocator();
+
+    // BASE is only page aligned, so the first 4-page aligned start is 0x4000
+    let addr = allocator.alloc_pages(1, 4 * PAGE_SIZE).unwrap();
+    assert_eq!(addr, 0x4000);
+    assert_eq!(allocator.alloc_pages(1, PAGE_SIZE), Ok(BASE));
+}
+
+#[test]
+fn test_alloc_at_and_dealloc() {
+    let allocator = new_allocator();
+
+    let addr = BASE + 4 * PAGE_SIZE;
+    assert_eq!(allocator.alloc_pages_at(addr, 4, PAGE_SIZE), Ok(addr));
+    assert_eq!(
+        allocator.alloc_pages_at(addr + PAGE_SIZE, 1, PAGE_SIZE),
+        Err(AllocError::NoMemory)
+    );
+    assert_eq!(
+        allocator.alloc_pages_at(BASE + 18 * PAGE_SIZE, 4, PAGE_SIZE),
+        Err(AllocError::NoMemory)
+    );
+
+    let stats = allocator.stats();
+    assert_eq!(stats.free_pages, TOTAL_PAGES - 4);
+    assert_eq!(stats.largest_free_block, TOTAL_PAGES - 8);
+
+    // a size of 0 frees the whole run
+    allocator.dealloc_pages(addr, 0);
+    assert_eq!(allocator.stats().largest_free_block, TOTAL_PAGES);
+    assert_eq!(allocator.alloc_pages(TOTAL_PAGES, PAGE_SIZE), Ok(BASE));
+}
+
+#[test]
+fn test_invalid_params() {
+    let allocator = new_allocator();
+
+    assert_eq!(allocator.alloc_pages(0, PAGE_SIZE), Err(AllocError::InvalidParam));
+    assert_eq!(allocator.alloc_pages(1, 100), Err(AllocError::InvalidParam));
+    assert_eq!(
+        allocator.alloc_pages_at(BASE + 1, 1, PAGE_SIZE),
+        Err(AllocError::InvalidParam)
+    );
+    assert_eq!(
+        allocator.alloc_pages(TOTAL_PAGES + 1, PAGE_SIZE),
+        Err(AllocError::NoMemory)
+    );
+}
+
+#[test]
+fn test_invalid_dealloc() {
+    let allocator = new_allocator();
+    let addr = allocator.alloc_pages(4, PAGE_SIZE).unwrap();
+
+    // unknown, unaligned, out of range and mid-allocation addresses are ignored
+    allocator.dealloc_pages(BASE + 10 * PAGE_SIZE, 1);
+    allocator.dealloc_pages(addr + 1, 4);
+    allocator.dealloc_pages(BASE + TOTAL_PAGES * PAGE_SIZE, 1);
+    allocator.dealloc_pages(addr + PAGE_SIZE, 3);
+    assert_eq!(allocator.stats().used_pages, 4);
+
+    // a size mismatch frees nothing
+    allocator.dealloc_pages(addr, 2);
+    assert_eq!(allocator.stats().used_pages, 4);
+
+    allocator.dealloc_pages(addr, 4);
+    assert_eq!(allocator.stats().used_pages, 0);
+
+    // double free does not corrupt the accounting
+    let other = allocator.alloc_pages(4, PAGE_SIZE).unwrap();
+    assert_eq!(other, addr);
+    allocator.dealloc_pages(addr, 4);
+    allocator.dealloc_pages(addr, 4);
+    assert_eq!(allocator.stats().used_pages, 0);
+    assert_eq!(allocator.stats().free_pages, TOTAL_PAGES);
+}
+
+#[test]
+fn test_realloc_pages() {
+    let allocator = new_allocator();
+
+    let addr = allocator.alloc_pages(2, PAGE_SIZE).unwrap();
+    assert_eq!(allocator.realloc_pages(addr, 2, 6, PAGE_SIZE), Ok(addr));
+    assert_eq!(allocator.realloc_pages(addr, 6, 3, PAGE_SIZE), Ok(addr));
+    assert_eq!(allocator.stats().used_pages, 3);
+
+    // the pages after the allocation are taken: relocate
+    let blocker = allocator.alloc_pages(1, PAGE_SIZE).unwrap();
+    assert_eq!(blocker, addr + 3 * PAGE_SIZE);
+    let new_addr = allocator.realloc_pages(addr, 3, 4, PAGE_SIZE).unwrap();
+    assert_eq!(new_addr, addr + 4 * PAGE_SIZE);
+    assert_eq!(allocator.stats().used_pages, 8);
+
+    assert_eq!(
+        allocator.realloc_pages(addr, 2, 4, PAGE_SIZE),
+        Err(AllocError::InvalidParam)
+    );
+}
@@ -0,0 +1,164 @@
+#![cfg(feature = "buddy")]
+
+use std::sync::Arc;
+use std::thread;
+
+use allocator::AllocError;
+use axalloc::allocators::{BuddyAllocator, PageAllocator};
+
+const PAGE_SIZE: usize = 4096;
+const BASE: usize = 0x1000;
+
+#[test]
+fn test_alloc_contiguous_best_effort() {
+    // 24 pages are split into two free blocks: 16 pages at 0 and 8 pages at 16
+    let allocator = BuddyAllocator::new();
+    allocator.init(BASE, 24 * PAGE_SIZE).unwrap();
+
+    // the normal path needs a 32-page block, which does not exist
+    assert!(allocator.alloc_pages(24, PAGE_SIZE).is_err());
+
+    let addr = allocator.alloc_contiguous_best_effort(24).unwrap();
+    assert_eq!(addr, BASE);
+    assert!(allocator.alloc_pages(1, PAGE_SIZE).is_err());
+
+    // the blocks inside the run cannot be released or resized on their own
+    allocator.dealloc_pages(addr + 16 * PAGE_SIZE, 8);
+    assert_eq!(allocator.stats().used_pages, 24);
+    assert_eq!(allocator.realloc_pages(addr + 16 * PAGE_SIZE, 8, 4, PAGE_SIZE), Err(AllocError::InvalidParam));
+
+    // a single dealloc releases the whole run
+    allocator.dealloc_pages(addr, 24);
+    assert_eq!(allocator.alloc_contiguous_best_effort(24), Ok(BASE));
+}
+
+#[test]
+fn test_stats() {
+    let allocator = BuddyAllocator::new();
+    allocator.init(BASE, 24 * PAGE_SIZE).unwrap();
+
+    let stats = allocator.stats();
+    assert_eq!(stats.total_pages, 24);
+    assert_eq!(stats.used_pages, 0);
+    assert_eq!(stats.free_pages, 24);
+    assert_eq!(stats.largest_free_block, 16);
+
+    // a 3-page request takes 4 pages out of the 8-page block
+    let addr = allocator.alloc_pages(3, PAGE_SIZE).unwrap();
+    let stats = allocator.stats();
+    assert_eq!(stats.used_pages, 4);
+    assert_eq!(stats.free_pages, 20);
+    assert_eq!(stats.largest_free_block, 16);
+    assert_eq!(stats.fragmentation, 1.0 - 16.0 / 20.0);
+
+    allocator.dealloc_pages(addr, 3);
+    assert_eq!(allocator.stats().used_pages, 0);
+    assert_eq!(allocator.stats().largest_free_block, 16);
+}
+
+#[test]
+fn test_invalid_dealloc() {
+    let allocator = BuddyAllocator::new();
+    allocator.init(BASE, 24 * PAGE_SIZE).unwrap();
+    let addr = allocator.alloc_pages(3, PAGE_SIZE).unwrap();
+
+    allocator.dealloc_pages(addr + PAGE_SIZE, 1);
+    allocator.dealloc_pages(addr + 1, 3);
+    allocator.dealloc_pages(addr, 8);
+    assert_eq!(allocator.stats().used_pages, 4);
+
+    // the size is compared after rounding up to the block size
+    allocator.dealloc_pages(addr, 4);
+    assert_eq!(allocator.stats().used_pages, 0);
+
+    allocator.dealloc_pages(addr, 4);
+    assert_eq!(allocator.stats().used_pages, 0);
+    assert_eq!(allocator.stats().largest_free_block, 16);
+}
+
+#[test]
+fn test_init_races_with_alloc() {
+    let allocator = Arc::new(BuddyAllocator::new());
+    allocator.init(BASE, 24 * PAGE_SIZE).unwrap();
+
+    let worker = {
+        let allocator = allocator.clone();
+        thread::spawn(move || {
+            for _ in 0..10_000 {
+                if let Ok(addr) = allocator.alloc_pages(2, PAGE_SIZE) {
+                    assert!((BASE..BASE + 24 * PAGE_SIZE).contains(&addr));
+                    allocator.dealloc_pages(addr, 2);
+                }
+            }
+        })
+    };
+    for _ in 0..1_000 {
+        allocator.init(BASE, 24 * PAGE_SIZE).unwrap();
+    }
+    worker.join().unwrap();
+
+    // every page is free exactly once after the race
+    let stats = allocator.stats();
+    assert_eq!(stats.used_pages, 0);
+    assert_eq!(stats.largest_free_block, 16);
+    let mut pages: Vec<usize> = (0..24)
+        .map(|_| allocator.alloc_pages(1, PAGE_SIZE).unwrap())
+        .collect();
+    assert_eq!(allocator.alloc_pages(1, PAGE_SIZE), Err(AllocError::NoMemory));
+    pages.sort_unstable();
+    pages.dedup();
+    assert_eq!(pages.len(), 24);
+}
+
+#[test]
+fn test_realloc_pages() {
+    let allocator = BuddyAllocator::new();
+    allocator.init(BASE, 16 * PAGE_SIZE).unwrap();
+
+    // grow in place by absorbing the free upper buddies: 1 -> 2 -> 8 pages
+    let addr = allocator.alloc_pages(1, PAGE_SIZE).unwrap();
+    assert_eq!(allocator.realloc_pages(addr, 1, 2, PAGE_SIZE), Ok(addr));
+    assert_eq!(allocator.realloc_pages(addr, 2, 8, PAGE_SIZE), Ok(addr));
+    assert_eq!(allocator.stats().used_pages, 8);
+
+    // shrink in place gives the upper halves back
+    assert_eq!(allocator.realloc_pages(addr, 8, 3, PAGE_SIZE), Ok(addr));
+    assert_eq!(allocator.stats().used_pages, 4);
+
+    // the upper buddy is taken, so growing relocates and keeps the old block
+    let blocker = allocator.alloc_pages(4, PAGE_SIZE).unwrap();
+    assert_eq!(blocker, addr + 4 * PAGE_SIZE);
+    let new_addr = allocator.realloc_pages(addr, 4, 8, PAGE_SIZE).unwrap();
+    assert_eq!(new_addr, addr + 8 * PAGE_SIZE);
+    assert_eq!(allocator.stats().used_pages, 16);
+    allocator.dealloc_pages(addr, 4);
+
+    assert_eq!(
+        allocator.realloc_pages(addr, 4, 8, PAGE_SIZE),
+        Err(AllocError::InvalidParam)
+    );
+}
+
+#[test]
+fn test_lowest_address_first() {
+    let allocator = BuddyAllocator::new();
+    allocator.init(BASE, 16 * PAGE_SIZE).unwrap();
+
+    let addrs: Vec<usize> = (0..4)
+        .map(|_| allocator.alloc_pages(1, PAGE_SIZE).unwrap())
+        .collect();
+    assert_eq!(addrs, [0, 1, 2, 3].map(|i| BASE + i * PAGE_SIZE));
+
+    // freed blocks are reused lowest address first, not most recently freed
+    allocator.dealloc_pages(addrs[1], 1);
+    allocator.dealloc_pages(addrs[3], 1);
+    assert_eq!(allocator.alloc_pages(1, PAGE_SIZE), Ok(addrs[1]));
+    assert_eq!(allocator.alloc_pages(1, PAGE_SIZE), Ok(addrs[3]));
+
+    // repeated alloc/free cycles keep returning the same address
+    for _ in 0..3 {
+        let addr = allocator.alloc_pages(2, PAGE_SIZE).unwrap();
+        assert_eq!(addr, BASE + 4 * PAGE_SIZE);
+        allocator.dealloc_pages(addr, 2);
+    }
+}
@@ -0,0 +1,195 @@
+#![cfg(feature = "hybrid")]
+
+use std::sync::Arc;
+use std::thread;
+
+use axalloc::allocators::{HybridAllocator, PageAllocator};
+
+const PAGE_SIZE: usize = 4096;
+const TOTAL_PAGES: usize = 256;
+/// The lower half of the pages is served by the bitmap, the rest by the
+/// free-list.
+const SMALL_PAGES: usize = TOTAL_PAGES / 2;
+const LARGE_PAGES: usize = TOTAL_PAGES - SMALL_PAGES;
+const BASE: usize = 0x1000;
+
+fn new_allocator() -> HybridAllocator {
+    let allocator = HybridAllocator::new();
+    allocator.init(BASE, TOTAL_PAGES * PAGE_SIZE).unwrap();
+    allocator
+}
+
+#[test]
+fn test_region_stats_small_exhaustion() {
+    let allocator = new_allocator();
+
+    let (small, large) = allocator.region_stats();
+    assert_eq!(small.total_pages, SMALL_PAGES);
+    assert_eq!(small.free_pages, SMALL_PAGES);
+    assert_eq!(large.total_pages, LARGE_PAGES);
+    assert_eq!(large.free_pages, LARGE_PAGES);
+
+    let mut count = 0;
+    while allocator.alloc_pages(1, PAGE_SIZE).is_ok() {
+        count += 1;
+    }
+    assert_eq!(count, SMALL_PAGES);
+
+    let (small, large) = allocator.region_stats();
+    assert_eq!(small.used_pages, SMALL_PAGES);
+    assert_eq!(small.free_pages, 0);
+    assert_eq!(large.used_pages, 0);
+    assert_eq!(large.free_pages, LARGE_PAGES);
+    assert_eq!(large.largest_free_block, LARGE_PAGES);
+
+    // large allocations only come from the free-list region
+    let mut count = 0;
+    while let Ok(addr) = allocator.alloc_pages(64, PAGE_SIZE) {
+        assert!(addr >= BASE + SMALL_PAGES * PAGE_SIZE);
+        count += 1;
+    }
+    assert_eq!(count, LARGE_PAGES / 64);
+
+    // every page is in use, none is handed out twice
+    assert!(allocator.alloc_pages(64, PAGE_SIZE).is_err());
+    assert!(allocator.alloc_pages(1, PAGE_SIZE).is_err());
+    assert_eq!(allocator.stats().used_pages, TOTAL_PAGES);
+}
+
+#[test]
+fn test_stats() {
+    let allocator = new_allocator();
+
+    // the two regions are never merged into one block
+    let stats = allocator.stats();
+    assert_eq!(stats.total_pages, TOTAL_PAGES);
+    assert_eq!(stats.free_pages, TOTAL_PAGES);
+    assert_eq!(stats.largest_free_block, SMALL_PAGES);
+    assert_eq!(stats.fragmentation, 0.5);
+
+    let large = allocator.alloc_pages(64, PAGE_SIZE).unwrap();
+    let stats = allocator.stats();
+    assert_eq!(stats.used_pages, 64);
+    assert_eq!(stats.free_pages, TOTAL_PAGES - 64);
+    assert_eq!(stats.largest_free_block, SMALL_PAGES);
+    let small = [(); 2].map(|_| allocator.alloc_pages(63, PAGE_SIZE).unwrap());
+    assert_eq!(allocator.stats().largest_free_block, LARGE_PAGES - 64);
+    for addr in small {
+        allocator.dealloc_pages(addr, 63);
+    }
+
+    allocator.dealloc_pages(large, 64);
+    assert_eq!(allocator.stats().used_pages, 0);
+}
+
+#[test]
+fn test_small_alloc_first_fit() {
+    let allocator = new_allocator();
+    let page = |idx: usize| BASE + idx * PAGE_SIZE;
+
+    // free runs: 1, 3..=4, 6..=9, 11..
+    for idx in [0, 2, 5, 10] {
+        allocator.alloc_pages_at(page(idx), 1, PAGE_SIZE).unwrap();
+    }
+
+    // runs crossing a byte boundary of the bitmap are found too
+    assert_eq!(allocator.alloc_pages(4, PAGE_SIZE), Ok(page(6)));
+    assert_eq!(allocator.alloc_pages(2, PAGE_SIZE), Ok(page(3)));
+    assert_eq!(allocator.alloc_pages(1, PAGE_SIZE), Ok(page(1)));
+    assert_eq!(allocator.alloc_pages(20, PAGE_SIZE), Ok(page(11)));
+    assert_eq!(allocator.alloc_pages(63, PAGE_SIZE), Ok(page(31)));
+    assert!(allocator.alloc_pages(63, PAGE_SIZE).is_err());
+}
+
+#[test]
+fn test_small_alloc_fragmented() {
+    const PAGES: usize = 64 * 1024;
+    const NEEDED: usize = 63;
+
+    let allocator = HybridAllocator::new();
+    allocator.init(BASE, PAGES * PAGE_SIZE).unwrap();
+
+    // leave free runs one page shorter than requested in the bitmap region,
+    // the worst case for a scan that restarts at every page
+    for idx in (NEEDED - 1..PAGES / 2).step_by(NEEDED) {
+        allocator.alloc_pages_at(BASE + idx * PAGE_SIZE, 1, PAGE_SIZE).unwrap();
+    }
+
+    assert!(allocator.alloc_pages(NEEDED, PAGE_SIZE).is_err());
+    assert_eq!(allocator.alloc_pages(NEEDED - 1, PAGE_SIZE), Ok(BASE));
+}
+
+#[test]
+fn test_invalid_dealloc() {
+    let allocator = new_allocator();
+    let addr = allocator.alloc_pages(2, PAGE_SIZE).unwrap();
+
+    allocator.dealloc_pages(addr + PAGE_SIZE, 1);
+    allocator.dealloc_pages(addr + 1, 2);
+    allocator.dealloc_pages(addr, 1);
+    assert_eq!(allocator.stats().used_pages, 2);
+
+    allocator.dealloc_pages(addr, 2);
+    allocator.dealloc_pages(addr, 2);
+    assert_eq!(allocator.stats().used_pages, 0);
+
+    let large = allocator.alloc_pages(64, PAGE_SIZE).unwrap();
+    allocator.dealloc_pages(large, 32);
+    assert_eq!(allocator.stats().used_pages, 64);
+    allocator.dealloc_pages(large, 0);
+    allocator.dealloc_pages(large, 64);
+    assert_eq!(allocator.stats().used_pages, 0);
+}
+
+#[test]
+fn test_init_races_with_alloc() {
+    let allocator = Arc::new(new_allocator());
+
+    let worker = {
+        let allocator = allocator.clone();
+        thread::spawn(move || {
+            for _ in 0..10_000 {
+                if let Ok(addr) = allocator.alloc_pages(2, PAGE_SIZE) {
+                    assert!((BASE..BASE + TOTAL_PAGES * PAGE_SIZE).contains(&addr));
+                    allocator.dealloc_pages(addr, 2);
+                }
+            }
+        })
+    };
+    for _ in 0..1_000 {
+        allocator.init(BASE, TOTAL_PAGES * PAGE_SIZE).unwrap();
+    }
+    worker.join().unwrap();
+
+    // both regions are entirely free after the race
+    assert_eq!(allocator.stats().used_pages, 0);
+    let (small, large) = allocator.region_stats();
+    assert_eq!(small.free_pages, SMALL_PAGES);
+    assert_eq!(large.free_pages, LARGE_PAGES);
+    assert_eq!(large.free_blocks, 1);
+    assert_eq!(allocator.alloc_pages(2, PAGE_SIZE), Ok(BASE));
+    assert_eq!(allocator.stats().used_pages, 2);
+}
+
+#[test]
+fn test_realloc_pages() {
+    let allocator = new_allocator();
+
+    // small allocations grow and shrink within the bitmap
+    let small = allocator.alloc_pages(2, PAGE_SIZE).unwrap();
+    assert_eq!(allocator.realloc_pages(small, 2, 10, PAGE_SIZE), Ok(small));
+    assert_eq!(allocator.realloc_pages(small, 10, 1, PAGE_SIZE), Ok(small));
+    assert_eq!(allocator.stats().used_pages, 1);
+    allocator.dealloc_pages(small, 1);
+
+    // large allocations grow into the following free-list block
+    let large = allocator.alloc_pages(64, PAGE_SIZE).unwrap();
+    assert_eq!(allocator.realloc_pages(large, 64, 100, PAGE_SIZE), Ok(large));
+    assert_eq!(allocator.realloc_pages(large, 100, 70, PAGE_SIZE), Ok(large));
+    assert_eq!(allocator.region_stats().1.largest_free_block, LARGE_PAGES - 70);
+    assert_eq!(allocator.realloc_pages(large, 70, LARGE_PAGES, PAGE_SIZE), Ok(large));
+    assert_eq!(allocator.stats().used_pages, LARGE_PAGES);
+
+    // no room to grow anywhere
+    assert!(allocator.realloc_pages(large, LARGE_PAGES, LARGE_PAGES + 1, PAGE_SIZE).is_err());
+}
@@ -0,0 +1,57 @@
+#![cfg(feature = "runtime-switch")]
+
+use allocator::AllocError;
+use axalloc::allocators::runtime;
+
+const PAGE_SIZE: usize = 4096;
+const BASE: usize = 0x10_0000;
+const SIZE: usize = 256 * PAGE_SIZE;
+
+fn alloc_and_free() {
+    let addr = runtime::alloc_pages(4, PAGE_SIZE).unwrap();
+    assert!((BASE..BASE + SIZE).contains(&addr));
+    runtime::dealloc_pages(addr, 4);
+}
+
+#[test]
+fn test_switch_allocators() {
+    runtime::init(BASE, SIZE).unwrap();
+
+    // nothing selected yet: the built-in buddy allocator serves requests
+    assert!(runtime::is_enabled());
+    assert_eq!(runtime::current_allocator_name(), "buddy");
+    alloc_and_free();
+
+    let buddy = runtime::make_by_name("buddy").unwrap();
+    buddy.init(BASE, SIZE).unwrap();
+    runtime::set_runtime_allocator(buddy).unwrap();
+    assert_eq!(runtime::current_allocator_name(), "buddy");
+    alloc_and_free();
+
+    let hybrid = runtime::make_by_name("hybrid").unwrap();
+    hybrid.init(BASE, SIZE).unwrap();
+    runtime::set_runtime_allocator(hybrid).unwrap();
+    assert_eq!(runtime::current_allocator_name(), "hybrid");
+    alloc_and_free();
+
+    // pages still allocated from the current allocator block a switch
+    let addr = runtime::alloc_pages(1, PAGE_SIZE).unwrap();
+    let buddy = runtime::make_by_name("buddy").unwrap();
+    buddy.init(BASE, SIZE).unwrap();
+    assert_eq!(
+        runtime::set_runtime_allocator(buddy),
+        Err(AllocError::MemoryOverlap)
+    );
+    assert_eq!(
+        runtime::clear_runtime_allocator(),
+        Err(AllocError::MemoryOverlap)
+    );
+    assert_eq!(runtime::current_allocator_name(), "hybrid");
+    runtime::dealloc_pages(addr, 1);
+
+    runtime::clear_runtime_allocator().unwrap();
+    assert_eq!(runtime::current_allocator_name(), "buddy");
+    alloc_and_free();
+
+    assert!(runtime::make_by_name("unknown").is_err());
+}
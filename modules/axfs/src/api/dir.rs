@@ -71,18 +71,15 @@ impl<'a> Iterator for ReadDir<'a> {
                     }
                 }
             }
-            let entry = &self.dirent_buf[self.buf_pos];
+            let (name, entry_type) = fops::decode_dir_entry(&self.dirent_buf[self.buf_pos]);
             self.buf_pos += 1;
-            let name_bytes = entry.name_as_bytes();
-            if name_bytes == b"." || name_bytes == b".." {
+            if name == "." || name == ".." {
                 continue;
             }
-            let entry_name = unsafe { core::str::from_utf8_unchecked(name_bytes).into() };
-            let entry_type = entry.entry_type();
 
             return Some(Ok(DirEntry {
                 dir_path: self.path,
-                entry_name,
+                entry_name: name.into(),
                 entry_type,
             }));
         }
@@ -1,5 +1,6 @@
 //! Low-level filesystem operations.
 
+use alloc::string::String;
 use axerrno::{AxError, AxResult, ax_err, ax_err_type};
 use axfs_vfs::{VfsError, VfsNodeRef};
 use axio::SeekFrom;
@@ -34,6 +35,15 @@ pub struct Directory {
     entry_idx: usize,
 }
 
+/// An iterator over a buffer of [`DirEntry`]s filled by
+/// [`Directory::read_dir`], yielding the name and type of each entry.
+///
+/// Names are cut at the first NUL byte. A name that is not valid UTF-8 is
+/// truncated to its longest valid prefix.
+pub struct DirEntryIter<'a> {
+    entries: core::slice::Iter<'a, DirEntry>,
+}
+
 /// Options and flags which can be used to configure how a file is opened.
 #[derive(Clone)]
 pub struct OpenOptions {
@@ -252,6 +262,20 @@ impl File {
     pub fn get_attr(&self) -> AxResult<FileAttr> {
         self.access_node(Cap::empty())?.get_attr()
     }
+
+    /// Changes the current directory to the directory opened by this file,
+    /// whose absolute path is `path`.
+    ///
+    /// The directory is not looked up again by `path`, so it is the one that
+    /// this file refers to even if it has been renamed since it was opened.
+    pub fn set_as_current_dir(&self, path: &str) -> AxResult {
+        let node = self.access_node(Cap::empty())?.clone();
+        let mut abs_path = String::from(path);
+        if !abs_path.ends_with('/') {
+            abs_path += "/";
+        }
+        crate::root::set_current_dir_node(node, abs_path)
+    }
 }
 
 impl Directory {
@@ -353,6 +377,39 @@ impl Directory {
     }
 }
 
+impl<'a> DirEntryIter<'a> {
+    /// Creates an iterator over the given filled entries, usually the first
+    /// `n` entries of the buffer passed to [`Directory::read_dir`].
+    pub fn new(entries: &'a [DirEntry]) -> Self {
+        Self {
+            entries: entries.iter(),
+        }
+    }
+}
+
+impl<'a> Iterator for DirEntryIter<'a> {
+    type Item = (&'a str, FileType);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next().map(decode_dir_entry)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+/// Returns the name and type of a filled [`DirEntry`], decoded as described
+/// in [`DirEntryIter`].
+pub(crate) fn decode_dir_entry(entry: &DirEntry) -> (&str, FileType) {
+    let bytes = entry.name_as_bytes();
+    let name = match core::str::from_utf8(bytes) {
+        Ok(name) => name,
+        Err(e) => core::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or_default(),
+    };
+    (name, entry.entry_type())
+}
+
 impl Drop for File {
     fn drop(&mut self) {
         unsafe { self.node.access_unchecked().release().ok() };
@@ -1,10 +1,11 @@
 use alloc::sync::Arc;
-use core::cell::UnsafeCell;
+use core::mem::ManuallyDrop;
 
 use axfs_vfs::{VfsDirEntry, VfsError, VfsNodePerm, VfsResult};
 use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps};
 use axsync::Mutex;
 use fatfs::{Dir, File, LossyOemCpConverter, NullTimeProvider, Read, Seek, SeekFrom, Write};
+use lazyinit::LazyInit;
 
 use crate::dev::Disk;
 
@@ -12,53 +13,106 @@ const BLOCK_SIZE: usize = 512;
 
 pub struct FatFileSystem {
     inner: fatfs::FileSystem<Disk, NullTimeProvider, LossyOemCpConverter>,
-    root_dir: UnsafeCell<Option<VfsNodeRef>>,
+    /// Serializes every access to `inner`, including the accesses made through
+    /// the `File`/`Dir` handles that borrow it.
+    lock: Mutex<()>,
+    root_dir: LazyInit<VfsNodeRef>,
 }
 
-pub struct FileWrapper<'a>(Mutex<File<'a, Disk, NullTimeProvider, LossyOemCpConverter>>);
-pub struct DirWrapper<'a>(Dir<'a, Disk, NullTimeProvider, LossyOemCpConverter>);
+pub struct FileWrapper<'a> {
+    fs: &'a FatFileSystem,
+    file: ManuallyDrop<Mutex<File<'a, Disk, NullTimeProvider, LossyOemCpConverter>>>,
+}
+
+pub struct DirWrapper<'a> {
+    fs: &'a FatFileSystem,
+    dir: ManuallyDrop<Dir<'a, Disk, NullTimeProvider, LossyOemCpConverter>>,
+}
 
+// SAFETY: `fatfs::FileSystem` keeps its disk and FS info in `RefCell`s, which
+// makes it `!Sync`. Every method of `FatFileSystem` that touches `inner` holds
+// `FatFileSystem::lock` for the whole access, so the `RefCell`s are never
+// accessed concurrently.
 unsafe impl Sync for FatFileSystem {}
 unsafe impl Send for FatFileSystem {}
+// SAFETY: a `File` borrows the `RefCell`s of the filesystem. `FileWrapper`
+// only uses it, and drops it (which may flush to disk), with `fs.lock` held.
 unsafe impl Send for FileWrapper<'_> {}
 unsafe impl Sync for FileWrapper<'_> {}
+// SAFETY: likewise, `DirWrapper` only uses and drops its `Dir` with `fs.lock`
+// held.
 unsafe impl Send for DirWrapper<'_> {}
 unsafe impl Sync for DirWrapper<'_> {}
 
 impl FatFileSystem {
+    pub fn new(disk: Disk) -> Self {
+        Self::try_new(disk).expect("failed to initialize FAT filesystem")
+    }
+
+    /// Formats `disk` and opens the new FAT filesystem on it.
     #[cfg(feature = "use-ramdisk")]
-    pub fn new(mut disk: Disk) -> Self {
+    pub fn try_new(mut disk: Disk) -> VfsResult<Self> {
         let opts = fatfs::FormatVolumeOptions::new();
-        fatfs::format_volume(&mut disk, opts).expect("failed to format volume");
-        let inner = fatfs::FileSystem::new(disk, fatfs::FsOptions::new())
-            .expect("failed to initialize FAT filesystem");
-        Self {
+        fatfs::format_volume(&mut disk, opts).map_err(as_vfs_err)?;
+        let inner = fatfs::FileSystem::new(disk, fatfs::FsOptions::new()).map_err(as_vfs_err)?;
+        Ok(Self {
             inner,
-            root_dir: UnsafeCell::new(None),
-        }
+            lock: Mutex::new(()),
+            root_dir: LazyInit::new(),
+        })
     }
 
+    /// Opens the FAT filesystem on `disk`, failing if it does not hold one.
     #[cfg(not(feature = "use-ramdisk"))]
-    pub fn new(disk: Disk) -> Self {
-        let inner = fatfs::FileSystem::new(disk, fatfs::FsOptions::new())
-            .expect("failed to initialize FAT filesystem");
-        Self {
+    pub fn try_new(disk: Disk) -> VfsResult<Self> {
+        let inner = fatfs::FileSystem::new(disk, fatfs::FsOptions::new()).map_err(as_vfs_err)?;
+        Ok(Self {
             inner,
-            root_dir: UnsafeCell::new(None),
-        }
+            lock: Mutex::new(()),
+            root_dir: LazyInit::new(),
+        })
     }
 
     pub fn init(&'static self) {
         // must be called before later operations
-        unsafe { *self.root_dir.get() = Some(Self::new_dir(self.inner.root_dir())) }
+        let _guard = self.lock.lock();
+        self.root_dir.init_once(self.new_dir(self.inner.root_dir()));
+    }
+
+    fn new_file(
+        &'static self,
+        file: File<'static, Disk, NullTimeProvider, LossyOemCpConverter>,
+    ) -> Arc<FileWrapper<'static>> {
+        Arc::new(FileWrapper {
+            fs: self,
+            file: ManuallyDrop::new(Mutex::new(file)),
+        })
     }
 
-    fn new_file(file: File<'_, Disk, NullTimeProvider, LossyOemCpConverter>) -> Arc<FileWrapper> {
-        Arc::new(FileWrapper(Mutex::new(file)))
+    fn new_dir(
+        &'static self,
+        dir: Dir<'static, Disk, NullTimeProvider, LossyOemCpConverter>,
+    ) -> Arc<DirWrapper<'static>> {
+        Arc::new(DirWrapper {
+            fs: self,
+            dir: ManuallyDrop::new(dir),
+        })
     }
+}
+
+impl Drop for FileWrapper<'_> {
+    fn drop(&mut self) {
+        let _guard = self.fs.lock.lock();
+        // SAFETY: `file` is never used again after this point.
+        unsafe { ManuallyDrop::drop(&mut self.file) };
+    }
+}
 
-    fn new_dir(dir: Dir<'_, Disk, NullTimeProvider, LossyOemCpConverter>) -> Arc<DirWrapper> {
-        Arc::new(DirWrapper(dir))
+impl Drop for DirWrapper<'_> {
+    fn drop(&mut self) {
+        let _guard = self.fs.lock.lock();
+        // SAFETY: `dir` is never used again after this point.
+        unsafe { ManuallyDrop::drop(&mut self.dir) };
     }
 }
 
@@ -66,7 +120,8 @@ impl VfsNodeOps for FileWrapper<'static> {
     axfs_vfs::impl_vfs_non_dir_default! {}
 
     fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
-        let size = self.0.lock().seek(SeekFrom::End(0)).map_err(as_vfs_err)?;
+        let _guard = self.fs.lock.lock();
+        let size = self.file.lock().seek(SeekFrom::End(0)).map_err(as_vfs_err)?;
         let blocks = size.div_ceil(BLOCK_SIZE as u64);
         // FAT fs doesn't support permissions, we just set everything to 755
         let perm = VfsNodePerm::from_bits_truncate(0o755);
@@ -74,19 +129,22 @@ impl VfsNodeOps for FileWrapper<'static> {
     }
 
     fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
-        let mut file = self.0.lock();
+        let _guard = self.fs.lock.lock();
+        let mut file = self.file.lock();
         file.seek(SeekFrom::Start(offset)).map_err(as_vfs_err)?; // TODO: more efficient
         file.read(buf).map_err(as_vfs_err)
     }
 
     fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
-        let mut file = self.0.lock();
+        let _guard = self.fs.lock.lock();
+        let mut file = self.file.lock();
         file.seek(SeekFrom::Start(offset)).map_err(as_vfs_err)?; // TODO: more efficient
         file.write(buf).map_err(as_vfs_err)
     }
 
     fn truncate(&self, size: u64) -> VfsResult {
-        let mut file = self.0.lock();
+        let _guard = self.fs.lock.lock();
+        let mut file = self.file.lock();
         let current_size = file.seek(SeekFrom::End(0)).map_err(as_vfs_err)?;
 
         if size <= current_size {
@@ -124,9 +182,10 @@ impl VfsNodeOps for DirWrapper<'static> {
     }
 
     fn parent(&self) -> Option<VfsNodeRef> {
-        self.0
+        let _guard = self.fs.lock.lock();
+        self.dir
             .open_dir("..")
-            .map_or(None, |dir| Some(FatFileSystem::new_dir(dir)))
+            .map_or(None, |dir| Some(self.fs.new_dir(dir)))
     }
 
     fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
@@ -139,11 +198,12 @@ impl VfsNodeOps for DirWrapper<'static> {
             return self.lookup(rest);
         }
 
+        let _guard = self.fs.lock.lock();
         // TODO: use `fatfs::Dir::find_entry`, but it's not public.
-        if let Ok(file) = self.0.open_file(path) {
-            Ok(FatFileSystem::new_file(file))
-        } else if let Ok(dir) = self.0.open_dir(path) {
-            Ok(FatFileSystem::new_dir(dir))
+        if let Ok(file) = self.dir.open_file(path) {
+            Ok(self.fs.new_file(file))
+        } else if let Ok(dir) = self.dir.open_dir(path) {
+            Ok(self.fs.new_dir(dir))
         } else {
             Err(VfsError::NotFound)
         }
@@ -159,13 +219,14 @@ impl VfsNodeOps for DirWrapper<'static> {
             return self.create(rest, ty);
         }
 
+        let _guard = self.fs.lock.lock();
         match ty {
             VfsNodeType::File => {
-                self.0.create_file(path).map_err(as_vfs_err)?;
+                self.dir.create_file(path).map_err(as_vfs_err)?;
                 Ok(())
             }
             VfsNodeType::Dir => {
-                self.0.create_dir(path).map_err(as_vfs_err)?;
+                self.dir.create_dir(path).map_err(as_vfs_err)?;
                 Ok(())
             }
             _ => Err(VfsError::Unsupported),
@@ -179,11 +240,13 @@ impl VfsNodeOps for DirWrapper<'static> {
         if let Some(rest) = path.strip_prefix("./") {
             return self.remove(rest);
         }
-        self.0.remove(path).map_err(as_vfs_err)
+        let _guard = self.fs.lock.lock();
+        self.dir.remove(path).map_err(as_vfs_err)
     }
 
     fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
-        let mut iter = self.0.iter().skip(start_idx);
+        let _guard = self.fs.lock.lock();
+        let mut iter = self.dir.iter().skip(start_idx);
         for (i, out_entry) in dirents.iter_mut().enumerate() {
             let x = iter.next();
             match x {
@@ -210,16 +273,16 @@ impl VfsNodeOps for DirWrapper<'static> {
             src_path, dst_path
         );
 
-        self.0
-            .rename(src_path, &self.0, dst_path)
+        let _guard = self.fs.lock.lock();
+        self.dir
+            .rename(src_path, &self.dir, dst_path)
             .map_err(as_vfs_err)
     }
 }
 
 impl VfsOps for FatFileSystem {
     fn root_dir(&self) -> VfsNodeRef {
-        let root_dir = unsafe { (*self.root_dir.get()).as_ref().unwrap() };
-        root_dir.clone()
+        self.root_dir.clone()
     }
 }
 
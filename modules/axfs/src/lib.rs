@@ -29,18 +29,107 @@ extern crate alloc;
 mod dev;
 mod fs;
 mod mounts;
+#[cfg(feature = "ramfs")]
+mod quota;
+mod registry;
 mod root;
 
 pub mod api;
 pub mod fops;
 
+#[cfg(feature = "ramfs")]
+pub use quota::Quota;
+pub use registry::{FsFactory, register_fs_type};
+pub use root::{list_mounts, mount_at, mount_read_only_at, unmount};
+
+use alloc::{format, string::String};
 use axdriver::{AxDeviceContainer, prelude::*};
+use axerrno::AxResult;
+
+/// Returns the conventional name of the block device at `index`: `vda`,
+/// `vdb`, ..., `vdz`, then `vdaa`, `vdab`, and so on.
+pub fn get_device_name(index: usize) -> String {
+    let mut suffix = String::new();
+    let mut n = index + 1;
+    while n > 0 {
+        n -= 1;
+        suffix.insert(0, (b'a' + (n % 26) as u8) as char);
+        n /= 26;
+    }
+    format!("vd{}", suffix)
+}
+
+/// Mounts the built-in disk filesystem on `dev` at the absolute `path`,
+/// creating its parent directory if needed.
+///
+/// [`init_filesystems`] does this for every block device but the first one.
+/// Returns an error if `dev` does not hold a filesystem of the built-in type.
+pub fn mount_block_device(path: &str, dev: AxBlockDevice) -> AxResult {
+    self::root::mount_disk(path, self::dev::Disk::new(dev))
+}
+
+/// Returns the byte quota of the ramfs mounted at `/tmp`. It is unlimited
+/// unless set with [`Quota::set_quota`].
+#[cfg(feature = "ramfs")]
+pub fn tmp_quota() -> &'static Quota {
+    &mounts::TMP_QUOTA
+}
 
 /// Initializes filesystems by block devices.
-pub fn init_filesystems(mut blk_devs: AxDeviceContainer<AxBlockDevice>) {
+///
+/// The first device becomes the root filesystem, and each remaining device
+/// is mounted at `/mnt/<name>`, where `<name>` is given by [`get_device_name`].
+pub fn init_filesystems(blk_devs: AxDeviceContainer<AxBlockDevice>) {
+    init_filesystems_inner(blk_devs, None)
+}
+
+/// Like [`init_filesystems`], but every block device holds the filesystem
+/// type `fs_type` registered with [`register_fs_type`], instead of the one
+/// selected by cargo features.
+///
+/// # Panics
+///
+/// Panics if `fs_type` is not registered or fails to create the root
+/// filesystem.
+pub fn init_filesystems_as(blk_devs: AxDeviceContainer<AxBlockDevice>, fs_type: &str) {
+    init_filesystems_inner(blk_devs, Some(fs_type))
+}
+
+fn init_filesystems_inner(mut blk_devs: AxDeviceContainer<AxBlockDevice>, fs_type: Option<&str>) {
     info!("Initialize filesystems...");
 
     let dev = blk_devs.take_one().expect("No block device found!");
     info!("  use block device 0: {:?}", dev.device_name());
-    self::root::init_rootfs(self::dev::Disk::new(dev));
+    let disk = self::dev::Disk::new(dev);
+    match fs_type {
+        Some(name) => {
+            let (_, main_fs) =
+                registry::new_fs(name, disk).expect("failed to create the root filesystem");
+            self::root::init_rootfs_with(main_fs);
+        }
+        None => self::root::init_rootfs(disk),
+    }
+
+    let mut index = 1;
+    while let Some(dev) = blk_devs.take_one() {
+        let mount_point = format!("/mnt/{}", get_device_name(index));
+        info!(
+            "  mount block device {}: {:?} at {}",
+            index,
+            dev.device_name(),
+            mount_point
+        );
+        let res = match fs_type {
+            Some(name) => registry::new_fs(name, self::dev::Disk::new(dev))
+                .and_then(|(name, fs)| self::root::mount_fs(&mount_point, name, fs)),
+            None => mount_block_device(&mount_point, dev),
+        };
+        if let Err(e) = res {
+            warn!(
+                "failed to mount block device {} at {}: {:?}",
+                index, mount_point, e
+            );
+        }
+        index += 1;
+    }
 }
@@ -2,18 +2,23 @@ use alloc::sync::Arc;
 use axfs_vfs::{VfsNodeType, VfsOps, VfsResult};
 
 use crate::fs;
+#[cfg(feature = "ramfs")]
+use crate::quota::{Quota, QuotaFileSystem};
 
 #[cfg(feature = "devfs")]
 pub(crate) fn devfs() -> Arc<fs::devfs::DeviceFileSystem> {
     let null = fs::devfs::NullDev;
     let zero = fs::devfs::ZeroDev;
-    let urandom = fs::devfs::UrandomDev::default();
+    // No entropy pool here: `/dev/random` is the same PRNG as `/dev/urandom`,
+    // shared so that the two do not yield the same sequence.
+    let urandom = Arc::new(fs::devfs::UrandomDev::default());
     let bar = fs::devfs::ZeroDev;
     let devfs = fs::devfs::DeviceFileSystem::new();
     let foo_dir = devfs.mkdir("foo");
     devfs.add("null", Arc::new(null));
     devfs.add("zero", Arc::new(zero));
-    devfs.add("urandom", Arc::new(urandom));
+    devfs.add("random", urandom.clone());
+    devfs.add("urandom", urandom);
     foo_dir.add("bar", Arc::new(bar));
     Arc::new(devfs)
 }
@@ -23,6 +28,15 @@ pub(crate) fn ramfs() -> Arc<fs::ramfs::RamFileSystem> {
     Arc::new(fs::ramfs::RamFileSystem::new())
 }
 
+/// Quota of the ramfs mounted at `/tmp`.
+#[cfg(feature = "ramfs")]
+pub(crate) static TMP_QUOTA: Quota = Quota::new();
+
+#[cfg(feature = "ramfs")]
+pub(crate) fn tmpfs() -> Arc<QuotaFileSystem> {
+    Arc::new(QuotaFileSystem::new(ramfs(), &TMP_QUOTA))
+}
+
 #[cfg(feature = "procfs")]
 pub(crate) fn procfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
     let procfs = fs::ramfs::RamFileSystem::new();
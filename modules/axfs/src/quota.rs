@@ -0,0 +1,199 @@
+//! Byte quota for in-memory filesystems.
+//!
+//! A [`QuotaFileSystem`] wraps another filesystem and charges every byte a
+//! file grows by against a shared [`Quota`]. Writes and truncations that
+//! would exceed the limit fail with [`StorageFull`](axerrno::AxError::StorageFull)
+//! (`ENOSPC`), and the bytes are given back when a file shrinks or is removed.
+
+use alloc::sync::Arc;
+use axerrno::{AxResult, ax_err};
+use axfs_vfs::{VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps, VfsResult};
+use axsync::Mutex;
+
+use crate::root::{lookup_in_mount, mount_depth};
+
+/// Total size limit of the files in a filesystem.
+pub struct Quota {
+    /// `(limit, used)` in bytes, `None` means unlimited.
+    inner: Mutex<(Option<u64>, u64)>,
+}
+
+impl Quota {
+    /// Creates an unlimited quota.
+    pub const fn new() -> Self {
+        Self {
+            inner: Mutex::new((None, 0)),
+        }
+    }
+
+    /// Sets the limit in bytes, `None` removes it.
+    ///
+    /// Lowering the limit below the current usage does not remove any data,
+    /// it only makes further growth fail.
+    pub fn set_quota(&self, bytes: Option<u64>) {
+        self.inner.lock().0 = bytes;
+    }
+
+    /// Returns the limit in bytes, or `None` if unlimited.
+    pub fn quota(&self) -> Option<u64> {
+        self.inner.lock().0
+    }
+
+    /// Returns the number of bytes currently charged to the quota.
+    pub fn usage(&self) -> u64 {
+        self.inner.lock().1
+    }
+
+    /// Runs `f`, which changes a file from `old_size` to `new_size` bytes as
+    /// returned by `sizes`, charging or releasing the difference. The quota
+    /// is held locked while the sizes are read and during `f`, so concurrent
+    /// growth cannot overshoot the limit or be charged twice.
+    fn resize<T>(
+        &self,
+        sizes: impl FnOnce() -> AxResult<(u64, u64)>,
+        f: impl FnOnce() -> AxResult<T>,
+    ) -> AxResult<T> {
+        let mut inner = self.inner.lock();
+        let (limit, used) = *inner;
+        let (old_size, new_size) = sizes()?;
+        if new_size > old_size && limit.is_some_and(|l| used + (new_size - old_size) > l) {
+            return ax_err!(StorageFull);
+        }
+        let res = f()?;
+        // never below zero, even if the old size was not charged
+        inner.1 = (used + new_size).saturating_sub(old_size);
+        Ok(res)
+    }
+}
+
+impl Default for Quota {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A filesystem whose total file size is limited by a [`Quota`].
+pub(crate) struct QuotaFileSystem {
+    inner: Arc<dyn VfsOps>,
+    quota: &'static Quota,
+}
+
+struct QuotaNode {
+    inner: VfsNodeRef,
+    quota: &'static Quota,
+    /// Number of levels below the root of the filesystem.
+    depth: usize,
+}
+
+impl QuotaFileSystem {
+    pub fn new(inner: Arc<dyn VfsOps>, quota: &'static Quota) -> Self {
+        Self { inner, quota }
+    }
+}
+
+impl VfsOps for QuotaFileSystem {
+    fn mount(&self, path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        self.inner.mount(path, mount_point)
+    }
+
+    fn umount(&self) -> VfsResult {
+        self.inner.umount()
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        QuotaNode::wrap(self.inner.root_dir(), self.quota, 0)
+    }
+}
+
+impl QuotaNode {
+    fn wrap(inner: VfsNodeRef, quota: &'static Quota, depth: usize) -> VfsNodeRef {
+        Arc::new(Self {
+            inner,
+            quota,
+            depth,
+        })
+    }
+
+    fn size(&self) -> VfsResult<u64> {
+        Ok(self.inner.get_attr()?.size())
+    }
+}
+
+impl VfsNodeOps for QuotaNode {
+    fn open(&self) -> VfsResult {
+        self.inner.open()
+    }
+
+    fn release(&self) -> VfsResult {
+        self.inner.release()
+    }
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        self.inner.get_attr()
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        self.inner.read_at(offset, buf)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let sizes = || {
+            let size = self.size()?;
+            Ok((size, size.max(offset + buf.len() as u64)))
+        };
+        self.quota
+            .resize(sizes, || self.inner.write_at(offset, buf))
+    }
+
+    fn fsync(&self) -> VfsResult {
+        self.inner.fsync()
+    }
+
+    fn truncate(&self, size: u64) -> VfsResult {
+        self.quota
+            .resize(|| Ok((self.size()?, size)), || self.inner.truncate(size))
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        let parent = self.inner.parent()?;
+        // the parent of the root is on another filesystem
+        Some(match self.depth.checked_sub(1) {
+            Some(depth) => Self::wrap(parent, self.quota, depth),
+            None => parent,
+        })
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        lookup_in_mount(self.inner.clone(), self.depth, path, |node, depth| {
+            Self::wrap(node, self.quota, depth)
+        })
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        self.inner.create(path, ty)
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        if mount_depth(self.depth, path).is_none() {
+            // `..` climbs out of the filesystem, nothing was charged
+            return self.inner.remove(path);
+        }
+        let sizes = || {
+            let attr = self.inner.clone().lookup(path)?.get_attr()?;
+            Ok((if attr.is_file() { attr.size() } else { 0 }, 0))
+        };
+        self.quota.resize(sizes, || self.inner.remove(path))
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        self.inner.read_dir(start_idx, dirents)
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        self.inner.rename(src_path, dst_path)
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self.inner.as_any()
+    }
+}
@@ -0,0 +1,41 @@
+//! Runtime registry of filesystem types.
+//!
+//! Unlike the `myfs` feature, which replaces the filesystem at compile time,
+//! a type registered here can be selected by name when calling
+//! [`init_filesystems_as`](crate::init_filesystems_as).
+
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use axerrno::{AxError, AxResult, ax_err};
+use axfs_vfs::{VfsOps, VfsResult};
+use axsync::Mutex;
+
+use crate::dev::Disk;
+
+/// Creates a filesystem on a disk.
+pub type FsFactory = Box<dyn Fn(Disk) -> VfsResult<Arc<dyn VfsOps>> + Send + Sync>;
+
+static FS_TYPES: Mutex<Vec<(&'static str, FsFactory)>> = Mutex::new(Vec::new());
+
+/// Registers the filesystem type `name`, created by `factory`.
+///
+/// Returns [`AlreadyExists`](AxError::AlreadyExists) if `name` is already
+/// registered.
+pub fn register_fs_type(name: &'static str, factory: FsFactory) -> AxResult {
+    let mut fs_types = FS_TYPES.lock();
+    if fs_types.iter().any(|(n, _)| *n == name) {
+        return ax_err!(AlreadyExists, "filesystem type already registered");
+    }
+    fs_types.push((name, factory));
+    Ok(())
+}
+
+/// Creates a filesystem of the registered type `name` on `disk`. Returns the
+/// type name along with the filesystem.
+pub(crate) fn new_fs(name: &str, disk: Disk) -> AxResult<(&'static str, Arc<dyn VfsOps>)> {
+    let fs_types = FS_TYPES.lock();
+    let (fs_type, factory) = fs_types
+        .iter()
+        .find(|(n, _)| *n == name)
+        .ok_or(AxError::NotFound)?;
+    Ok((*fs_type, factory(disk)?))
+}
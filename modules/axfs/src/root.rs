@@ -4,9 +4,11 @@
 
 use alloc::{string::String, sync::Arc, vec::Vec};
 use axerrno::{AxError, AxResult, ax_err};
-use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps, VfsResult};
+use axfs_vfs::{VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef, VfsNodeType};
+use axfs_vfs::{VfsOps, VfsResult};
 use axns::{ResArc, def_resource};
 use axsync::Mutex;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use lazyinit::LazyInit;
 
 use crate::{api::FileType, fs, mounts};
@@ -17,20 +19,49 @@ def_resource! {
 }
 
 struct MountPoint {
-    path: &'static str,
+    path: String,
+    fs_type: &'static str,
     fs: Arc<dyn VfsOps>,
+    read_only: bool,
+    /// Number of files and directories currently opened under the mount.
+    open_count: Arc<AtomicUsize>,
 }
 
+/// A node of a mounted filesystem, which keeps track of how many nodes of the
+/// mount are open so that it cannot be unmounted under them.
+struct MountedNode {
+    inner: VfsNodeRef,
+    open_count: Arc<AtomicUsize>,
+    /// Number of levels below the mount root.
+    depth: usize,
+}
+
+/// A node of a read-only mount. Reads are forwarded to the inner node, while
+/// every modification fails with [`PermissionDenied`](AxError::PermissionDenied).
+/// The second field is the number of levels below the mount root.
+struct ReadOnlyNode(VfsNodeRef, usize);
+
 struct RootDirectory {
     main_fs: Arc<dyn VfsOps>,
-    mounts: Vec<MountPoint>,
+    mounts: Mutex<Vec<MountPoint>>,
 }
 
 static ROOT_DIR: LazyInit<Arc<RootDirectory>> = LazyInit::new();
 
+/// Maximum length of a whole path, in bytes.
+const PATH_MAX: usize = 4096;
+/// Maximum length of a single path component, in bytes.
+const NAME_MAX: usize = 255;
+
 impl MountPoint {
-    pub fn new(path: &'static str, fs: Arc<dyn VfsOps>) -> Self {
-        Self { path, fs }
+    pub fn new(path: &str, fs_type: &'static str, fs: Arc<dyn VfsOps>, read_only: bool) -> Self {
+        Self {
+            path: path.into(),
+            fs_type,
+            fs,
+            read_only,
+            open_count: Arc::new(AtomicUsize::new(0)),
+        }
     }
 }
 
@@ -44,38 +75,70 @@ impl RootDirectory {
     pub const fn new(main_fs: Arc<dyn VfsOps>) -> Self {
         Self {
             main_fs,
-            mounts: Vec::new(),
+            mounts: Mutex::new(Vec::new()),
         }
     }
 
-    pub fn mount(&mut self, path: &'static str, fs: Arc<dyn VfsOps>) -> AxResult {
+    pub fn mount(
+        &self,
+        path: &str,
+        fs_type: &'static str,
+        fs: Arc<dyn VfsOps>,
+        read_only: bool,
+    ) -> AxResult {
         if path == "/" {
             return ax_err!(InvalidInput, "cannot mount root filesystem");
         }
         if !path.starts_with('/') {
             return ax_err!(InvalidInput, "mount path must start with '/'");
         }
-        if self.mounts.iter().any(|mp| mp.path == path) {
+        let mut mounts = self.mounts.lock();
+        if mounts.iter().any(|mp| mp.path == path) {
             return ax_err!(InvalidInput, "mount point already exists");
         }
         // create the mount point in the main filesystem if it does not exist
-        self.main_fs.root_dir().create(path, FileType::Dir)?;
+        match self.main_fs.root_dir().lookup(path) {
+            Ok(_) => {}
+            Err(AxError::NotFound) => self.main_fs.root_dir().create(path, FileType::Dir)?,
+            Err(e) => return Err(e),
+        }
         fs.mount(path, self.main_fs.root_dir().lookup(path)?)?;
-        self.mounts.push(MountPoint::new(path, fs));
+        mounts.push(MountPoint::new(path, fs_type, fs, read_only));
+        Ok(())
+    }
+
+    pub fn umount(&self, path: &str) -> AxResult {
+        let mut mounts = self.mounts.lock();
+        let idx = mounts
+            .iter()
+            .position(|mp| mp.path == path)
+            .ok_or(AxError::NotFound)?;
+        if mounts.iter().any(|mp| is_under(&mp.path, path)) {
+            return ax_err!(ResourceBusy, "another filesystem is mounted under it");
+        }
+        if mounts[idx].open_count.load(Ordering::Acquire) > 0 {
+            return ax_err!(ResourceBusy, "files under the mount point are open");
+        }
+        // `MountPoint::drop` calls `VfsOps::umount`
+        mounts.remove(idx);
         Ok(())
     }
 
-    pub fn _umount(&mut self, path: &str) {
-        self.mounts.retain(|mp| mp.path != path);
+    pub fn mounts(&self) -> Vec<(String, &'static str)> {
+        self.mounts
+            .lock()
+            .iter()
+            .map(|mp| (mp.path.clone(), mp.fs_type))
+            .collect()
     }
 
     pub fn contains(&self, path: &str) -> bool {
-        self.mounts.iter().any(|mp| mp.path == path)
+        self.mounts.lock().iter().any(|mp| mp.path == path)
     }
 
     fn lookup_mounted_fs<F, T>(&self, path: &str, f: F) -> AxResult<T>
     where
-        F: FnOnce(Arc<dyn VfsOps>, &str) -> AxResult<T>,
+        F: FnOnce(Arc<dyn VfsOps>, &str, bool, Option<Arc<AtomicUsize>>) -> AxResult<T>,
     {
         debug!("lookup at root: {}", path);
         let path = path.trim_matches('/');
@@ -83,24 +146,25 @@ impl RootDirectory {
             return self.lookup_mounted_fs(rest, f);
         }
 
-        let mut idx = 0;
+        let mut fs = self.main_fs.clone();
+        let mut read_only = false;
+        let mut open_count = None;
         let mut max_len = 0;
 
         // Find the filesystem that has the longest mounted path match
         // TODO: more efficient, e.g. trie
-        for (i, mp) in self.mounts.iter().enumerate() {
+        for mp in self.mounts.lock().iter() {
             // skip the first '/'
             if path.starts_with(&mp.path[1..]) && mp.path.len() - 1 > max_len {
                 max_len = mp.path.len() - 1;
-                idx = i;
+                fs = mp.fs.clone();
+                read_only = mp.read_only;
+                open_count = Some(mp.open_count.clone());
             }
         }
 
-        if max_len == 0 {
-            f(self.main_fs.clone(), path) // not matched any mount point
-        } else {
-            f(self.mounts[idx].fs.clone(), &path[max_len..]) // matched at `idx`
-        }
+        // `max_len == 0` means not matched any mount point
+        f(fs, &path[max_len..], read_only, open_count)
     }
 }
 
@@ -112,13 +176,27 @@ impl VfsNodeOps for RootDirectory {
     }
 
     fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
-        self.lookup_mounted_fs(path, |fs, rest_path| fs.root_dir().lookup(rest_path))
+        self.lookup_mounted_fs(path, |fs, rest_path, read_only, open_count| {
+            let Some(open_count) = open_count else {
+                return fs.root_dir().lookup(rest_path); // the main filesystem
+            };
+            lookup_in_mount(fs.root_dir(), 0, rest_path, |node, depth| {
+                let node = MountedNode::wrap(node, open_count, depth);
+                if read_only {
+                    ReadOnlyNode::wrap(node, depth)
+                } else {
+                    node
+                }
+            })
+        })
     }
 
     fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
-        self.lookup_mounted_fs(path, |fs, rest_path| {
+        self.lookup_mounted_fs(path, |fs, rest_path, read_only, _| {
             if rest_path.is_empty() {
                 Ok(()) // already exists
+            } else if read_only && mount_depth(0, rest_path).is_some() {
+                ax_err!(PermissionDenied)
             } else {
                 fs.root_dir().create(rest_path, ty)
             }
@@ -126,8 +204,8 @@ impl VfsNodeOps for RootDirectory {
     }
 
     fn remove(&self, path: &str) -> VfsResult {
-        self.lookup_mounted_fs(path, |fs, rest_path| {
-            if rest_path.is_empty() {
+        self.lookup_mounted_fs(path, |fs, rest_path, read_only, _| {
+            if rest_path.is_empty() || (read_only && mount_depth(0, rest_path).is_some()) {
                 ax_err!(PermissionDenied) // cannot remove mount points
             } else {
                 fs.root_dir().remove(rest_path)
@@ -136,8 +214,8 @@ impl VfsNodeOps for RootDirectory {
     }
 
     fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
-        self.lookup_mounted_fs(src_path, |fs, rest_path| {
-            if rest_path.is_empty() {
+        self.lookup_mounted_fs(src_path, |fs, rest_path, read_only, _| {
+            if rest_path.is_empty() || (read_only && mount_depth(0, rest_path).is_some()) {
                 ax_err!(PermissionDenied) // cannot rename mount points
             } else {
                 fs.root_dir().rename(rest_path, dst_path)
@@ -146,6 +224,279 @@ impl VfsNodeOps for RootDirectory {
     }
 }
 
+impl MountedNode {
+    fn wrap(inner: VfsNodeRef, open_count: Arc<AtomicUsize>, depth: usize) -> VfsNodeRef {
+        Arc::new(Self {
+            inner,
+            open_count,
+            depth,
+        })
+    }
+}
+
+impl VfsNodeOps for MountedNode {
+    fn open(&self) -> VfsResult {
+        self.inner.open()?;
+        self.open_count.fetch_add(1, Ordering::AcqRel);
+        Ok(())
+    }
+
+    fn release(&self) -> VfsResult {
+        self.open_count.fetch_sub(1, Ordering::AcqRel);
+        self.inner.release()
+    }
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        self.inner.get_attr()
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        self.inner.read_at(offset, buf)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        self.inner.write_at(offset, buf)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        self.inner.fsync()
+    }
+
+    fn truncate(&self, size: u64) -> VfsResult {
+        self.inner.truncate(size)
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        let parent = self.inner.parent()?;
+        // the parent of the mount root is not on the mount
+        Some(match self.depth.checked_sub(1) {
+            Some(depth) => Self::wrap(parent, self.open_count.clone(), depth),
+            None => parent,
+        })
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        lookup_in_mount(self.inner.clone(), self.depth, path, |node, depth| {
+            Self::wrap(node, self.open_count.clone(), depth)
+        })
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        self.inner.create(path, ty)
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        self.inner.remove(path)
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        self.inner.read_dir(start_idx, dirents)
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        self.inner.rename(src_path, dst_path)
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self.inner.as_any()
+    }
+}
+
+impl ReadOnlyNode {
+    fn wrap(node: VfsNodeRef, depth: usize) -> VfsNodeRef {
+        Arc::new(Self(node, depth))
+    }
+
+    /// Fails with `PermissionDenied` unless `path` climbs out of the mount,
+    /// in which case `f` modifies a node that is not read-only.
+    fn modify(&self, path: &str, f: impl FnOnce() -> VfsResult) -> VfsResult {
+        match mount_depth(self.1, path) {
+            Some(_) => ax_err!(PermissionDenied),
+            None => f(),
+        }
+    }
+}
+
+impl VfsNodeOps for ReadOnlyNode {
+    fn open(&self) -> VfsResult {
+        self.0.open()
+    }
+
+    fn release(&self) -> VfsResult {
+        self.0.release()
+    }
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let attr = self.0.get_attr()?;
+        let perm = attr.perm()
+            - (VfsNodePerm::OWNER_WRITE | VfsNodePerm::GROUP_WRITE | VfsNodePerm::OTHER_WRITE);
+        Ok(VfsNodeAttr::new(
+            perm,
+            attr.file_type(),
+            attr.size(),
+            attr.blocks(),
+        ))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        self.0.read_at(offset, buf)
+    }
+
+    fn write_at(&self, _offset: u64, _buf: &[u8]) -> VfsResult<usize> {
+        ax_err!(PermissionDenied)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        Ok(())
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        ax_err!(PermissionDenied)
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        let parent = self.0.parent()?;
+        // the parent of the mount root is not on the mount
+        Some(match self.1.checked_sub(1) {
+            Some(depth) => Self::wrap(parent, depth),
+            None => parent,
+        })
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        lookup_in_mount(self.0.clone(), self.1, path, Self::wrap)
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        self.modify(path, || self.0.create(path, ty))
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        self.modify(path, || self.0.remove(path))
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        self.0.read_dir(start_idx, dirents)
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        self.modify(src_path, || self.0.rename(src_path, dst_path))
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self.0.as_any()
+    }
+}
+
+/// Returns the number of levels below the mount root that `path` leads to,
+/// when looked up from a node `depth` levels below it, or `None` if a `..`
+/// climbs out of the mount.
+pub(crate) fn mount_depth(depth: usize, path: &str) -> Option<usize> {
+    path.split('/').try_fold(depth, |depth, name| match name {
+        "" | "." => Some(depth),
+        ".." => depth.checked_sub(1),
+        _ => Some(depth + 1),
+    })
+}
+
+/// Looks up `path` from `node`, which is `depth` levels below the root of a
+/// mounted filesystem.
+///
+/// A node found inside the mount is passed to `wrap` along with its depth.
+/// Once a `..` climbs out of the mount, the rest of the path is looked up
+/// from the parent of the mount root, and the node is returned unwrapped as
+/// it does not belong to the mount.
+pub(crate) fn lookup_in_mount(
+    node: VfsNodeRef,
+    depth: usize,
+    path: &str,
+    wrap: impl FnOnce(VfsNodeRef, usize) -> VfsNodeRef,
+) -> VfsResult<VfsNodeRef> {
+    if let Some(depth) = mount_depth(depth, path) {
+        return Ok(wrap(node.lookup(path)?, depth));
+    }
+
+    // find the `..` that climbs out of the mount, `path[..offset]` leads to
+    // the mount root
+    let mut depth = depth;
+    let mut offset = 0;
+    for name in path.split('/') {
+        match name {
+            ".." if depth == 0 => break,
+            ".." => depth -= 1,
+            "" | "." => {}
+            _ => depth += 1,
+        }
+        offset += name.len() + 1;
+    }
+    let parent = node
+        .lookup(&path[..offset])?
+        .parent()
+        .ok_or(AxError::NotFound)?;
+    match path[offset + 2..].trim_start_matches('/') {
+        "" => Ok(parent),
+        rest => parent.lookup(rest),
+    }
+}
+
+/// Returns whether `path` is strictly below the directory `dir`.
+fn is_under(path: &str, dir: &str) -> bool {
+    path.strip_prefix(dir.trim_end_matches('/'))
+        .is_some_and(|rest| rest.len() > 1 && rest.starts_with('/'))
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "myfs")] {
+        const DISK_FS_TYPE: &str = "myfs";
+    } else if #[cfg(feature = "fatfs")] {
+        const DISK_FS_TYPE: &str = "fatfs";
+    }
+}
+
+/// Creates the filesystem that lives on an additional (non-root) disk.
+///
+/// Fails if the disk does not hold a filesystem of the built-in type.
+fn new_disk_fs(disk: crate::dev::Disk) -> AxResult<Arc<dyn VfsOps>> {
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "myfs")] {
+            Ok(fs::myfs::new_myfs(disk))
+        } else if #[cfg(feature = "fatfs")] {
+            let fat_fs = Arc::new(fs::fatfs::FatFileSystem::try_new(disk)?);
+            // The nodes of a FAT filesystem borrow it for `'static`, and may
+            // outlive its mount point (e.g. an open file across `unmount`).
+            // Leak one reference so that the filesystem is never freed; each
+            // disk mounted here thus stays allocated even after unmounting.
+            //
+            // SAFETY: the pointer comes from `Arc::into_raw` and the leaked
+            // strong count is never given back, so it is valid forever.
+            let fat_fs_ref: &'static fs::fatfs::FatFileSystem =
+                unsafe { &*Arc::into_raw(fat_fs.clone()) };
+            fat_fs_ref.init();
+            Ok(fat_fs)
+        }
+    }
+}
+
+/// Mounts the built-in filesystem on `disk` at the absolute `path`, creating
+/// its parent directory if needed.
+pub(crate) fn mount_disk(path: &str, disk: crate::dev::Disk) -> AxResult {
+    mount_fs(path, DISK_FS_TYPE, new_disk_fs(disk)?)
+}
+
+/// Mounts `fs` at the absolute `path`, creating its parent directory if
+/// needed.
+pub(crate) fn mount_fs(path: &str, fs_type: &'static str, fs: Arc<dyn VfsOps>) -> AxResult {
+    if let Some((parent, _)) = path.trim_end_matches('/').rsplit_once('/') {
+        if !parent.is_empty() {
+            match create_dir(None, parent) {
+                Ok(()) | Err(AxError::AlreadyExists) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    ROOT_DIR.mount(path, fs_type, fs, false)
+}
+
 pub(crate) fn init_rootfs(disk: crate::dev::Disk) {
     cfg_if::cfg_if! {
         if #[cfg(feature = "myfs")] { // override the default filesystem
@@ -157,29 +508,34 @@ pub(crate) fn init_rootfs(disk: crate::dev::Disk) {
             let main_fs = FAT_FS.clone();
         }
     }
+    init_rootfs_with(main_fs);
+}
 
-    let mut root_dir = RootDirectory::new(main_fs);
+/// Sets up the root directory with `main_fs` mounted on `/`, along with the
+/// built-in pseudo filesystems.
+pub(crate) fn init_rootfs_with(main_fs: Arc<dyn VfsOps>) {
+    let root_dir = RootDirectory::new(main_fs);
 
     #[cfg(feature = "devfs")]
     root_dir
-        .mount("/dev", mounts::devfs())
+        .mount("/dev", "devfs", mounts::devfs(), false)
         .expect("failed to mount devfs at /dev");
 
     #[cfg(feature = "ramfs")]
     root_dir
-        .mount("/tmp", mounts::ramfs())
+        .mount("/tmp", "ramfs", mounts::tmpfs(), false)
         .expect("failed to mount ramfs at /tmp");
 
-    // Mount another ramfs as procfs
+    // Mount another ramfs as procfs, read-only
     #[cfg(feature = "procfs")]
     root_dir // should not fail
-        .mount("/proc", mounts::procfs().unwrap())
+        .mount("/proc", "procfs", mounts::procfs().unwrap(), true)
         .expect("fail to mount procfs at /proc");
 
-    // Mount another ramfs as sysfs
+    // Mount another ramfs as sysfs, read-only
     #[cfg(feature = "sysfs")]
     root_dir // should not fail
-        .mount("/sys", mounts::sysfs().unwrap())
+        .mount("/sys", "sysfs", mounts::sysfs().unwrap(), true)
         .expect("fail to mount sysfs at /sys");
 
     ROOT_DIR.init_once(Arc::new(root_dir));
@@ -187,6 +543,43 @@ pub(crate) fn init_rootfs(disk: crate::dev::Disk) {
     CURRENT_DIR_PATH.init_new(Mutex::new("/".into()));
 }
 
+/// Mounts `fs` at the absolute `path` of the root filesystem.
+///
+/// The mount point is created in the main filesystem if it does not exist.
+/// It can be used after [`init_filesystems`](crate::init_filesystems) to attach
+/// filesystems that are not backed by a block device, e.g. a
+/// `RamFileSystem` for tests. `fs_type` is the name reported by
+/// [`list_mounts`].
+pub fn mount_at(path: &str, fs_type: &'static str, fs: Arc<dyn VfsOps>) -> AxResult {
+    ROOT_DIR.mount(path, fs_type, fs, false)
+}
+
+/// Like [`mount_at`], but the filesystem is mounted read-only: its files can
+/// be read, while writes, truncation, creation, removal and renaming fail with
+/// [`PermissionDenied`](AxError::PermissionDenied).
+pub fn mount_read_only_at(path: &str, fs_type: &'static str, fs: Arc<dyn VfsOps>) -> AxResult {
+    ROOT_DIR.mount(path, fs_type, fs, true)
+}
+
+/// Unmounts the filesystem mounted at the absolute `path`.
+///
+/// Fails with [`NotFound`](AxError::NotFound) if nothing is mounted there,
+/// and with [`ResourceBusy`](AxError::ResourceBusy) if the current directory,
+/// an open file or another mount point is inside it.
+pub fn unmount(path: &str) -> AxResult {
+    let cwd = CURRENT_DIR_PATH.lock().clone();
+    if is_under(&cwd, path) || cwd.trim_end_matches('/') == path {
+        return ax_err!(ResourceBusy, "current directory is inside the mount point");
+    }
+    ROOT_DIR.umount(path)
+}
+
+/// Returns the mount point and filesystem type of every mounted filesystem,
+/// in mount order. The root filesystem is not included.
+pub fn list_mounts() -> Vec<(String, &'static str)> {
+    ROOT_DIR.mounts()
+}
+
 fn parent_node_of(dir: Option<&VfsNodeRef>, path: &str) -> VfsNodeRef {
     if path.starts_with('/') {
         ROOT_DIR.clone()
@@ -204,10 +597,21 @@ pub(crate) fn absolute_path(path: &str) -> AxResult<String> {
     }
 }
 
+/// Checks that `path` is at most [`PATH_MAX`] bytes long and none of its
+/// components exceeds [`NAME_MAX`] bytes, so names are never silently
+/// truncated by the underlying filesystem.
+pub(crate) fn validate_components(path: &str) -> AxResult {
+    if path.len() > PATH_MAX || path.split('/').any(|c| c.len() > NAME_MAX) {
+        return ax_err!(InvalidInput);
+    }
+    Ok(())
+}
+
 pub(crate) fn lookup(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
     if path.is_empty() {
         return ax_err!(NotFound);
     }
+    validate_components(path)?;
     let node = parent_node_of(dir, path).lookup(path)?;
     if path.ends_with('/') && !node.get_attr()?.is_dir() {
         ax_err!(NotADirectory)
@@ -222,6 +626,7 @@ pub(crate) fn create_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsN
     } else if path.ends_with('/') {
         return ax_err!(NotADirectory);
     }
+    validate_components(path)?;
     let parent = parent_node_of(dir, path);
     parent.create(path, VfsNodeType::File)?;
     parent.lookup(path)
@@ -292,6 +697,12 @@ pub(crate) fn set_current_dir(path: &str) -> AxResult {
     }
 
     let node = lookup(None, &abs_path)?;
+    set_current_dir_node(node, abs_path)
+}
+
+/// Makes `node` the current directory, where `abs_path` is the absolute path
+/// it is known by, ending with `/`.
+pub(crate) fn set_current_dir_node(node: VfsNodeRef, abs_path: String) -> AxResult {
     let attr = node.get_attr()?;
     if !attr.is_dir() {
         ax_err!(NotADirectory)
@@ -305,6 +716,8 @@ pub(crate) fn set_current_dir(path: &str) -> AxResult {
 }
 
 pub(crate) fn rename(old: &str, new: &str) -> AxResult {
+    validate_components(old)?;
+    validate_components(new)?;
     if parent_node_of(None, new).lookup(new).is_ok() {
         warn!("dst file already exist, now remove it");
         remove_file(None, new)?;
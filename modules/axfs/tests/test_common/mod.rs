@@ -127,6 +127,19 @@ fn test_create_file_dir() -> Result<()> {
     assert!(fs::metadata(dirname)?.is_dir());
     assert_err!(fs::create_dir(dirname), AlreadyExists);
 
+    // names longer than NAME_MAX and paths longer than PATH_MAX are rejected
+    let long_name = "x".repeat(256);
+    assert_err!(File::create(&long_name), InvalidInput);
+    assert_err!(fs::metadata(&long_name), InvalidInput);
+    let long_path = "a/".repeat(2049);
+    assert_err!(fs::metadata(&long_path), InvalidInput);
+    let max_name = "x".repeat(255);
+    fs::write(&max_name, contents)?;
+    assert_err!(fs::rename(&max_name, &long_name), InvalidInput);
+    assert_err!(fs::rename(&long_name, &max_name), InvalidInput);
+    assert_eq!(fs::read_to_string(&max_name)?, contents);
+    fs::remove_file(&max_name)?;
+
     println!("test_create_file_dir() OK!");
     Ok(())
 }
@@ -192,12 +205,25 @@ fn test_devfs_ramfs() -> Result<()> {
     assert!(file.write_all(&buf).is_ok());
     assert_eq!(buf, [0; N]);
 
+    // read and write /dev/random and /dev/urandom
+    let mut rands = [[0; 64]; 2];
+    for (path, rand) in ["/dev/random", "/dev/urandom"].into_iter().zip(&mut rands) {
+        let mut file = File::options().read(true).write(true).open(path)?;
+        assert_eq!(file.read(rand)?, rand.len());
+        assert_ne!(*rand, [0; 64]);
+        assert_eq!(file.write(&buf)?, N);
+    }
+    // both are backed by the same generator, so they don't repeat each other
+    assert_ne!(rands[0], rands[1]);
+
     // list /dev
     let dirents = fs::read_dir("/dev")?
         .map(|e| e.unwrap().file_name())
         .collect::<Vec<_>>();
     assert!(dirents.contains(&"null".into()));
     assert!(dirents.contains(&"zero".into()));
+    assert!(dirents.contains(&"random".into()));
+    assert!(dirents.contains(&"urandom".into()));
 
     // stat /dev
     let dname = "/dev";
@@ -248,6 +274,61 @@ fn test_devfs_ramfs() -> Result<()> {
     assert_eq!(fs::remove_dir("tmp/dir/.././dir///"), Ok(()));
     assert_eq!(fs::read_dir("tmp").unwrap().count(), 0);
 
+    // the ramfs at /tmp is limited by its quota
+    let quota = axfs::tmp_quota();
+    let used = quota.usage();
+    quota.set_quota(Some(used + 100));
+    assert_eq!(fs::write("/tmp/quota.txt", [1u8; 60]), Ok(()));
+    assert_eq!(quota.usage(), used + 60);
+    let mut file = OpenOptions::new().append(true).open("/tmp/quota.txt")?;
+    assert_err!(file.write(&[2u8; 41]), StorageFull);
+    assert_eq!(quota.usage(), used + 60);
+    assert_eq!(fs::read("/tmp/quota.txt")?, [1u8; 60]);
+    assert_eq!(file.write(&[2u8; 40])?, 40);
+    assert_err!(fs::write("/tmp/other.txt", "x"), StorageFull);
+    assert_eq!(fs::remove_file("/tmp/other.txt"), Ok(()));
+    drop(file);
+    assert_eq!(quota.usage(), used + 100);
+    assert_eq!(fs::write("/tmp/quota.txt", [0u8; 10]), Ok(())); // truncated first
+    assert_eq!(quota.usage(), used + 10);
+    assert_eq!(fs::remove_file("/tmp/quota.txt"), Ok(()));
+    assert_eq!(quota.usage(), used);
+
+    // files reached through `..` are not on /tmp and not charged to it
+    assert_eq!(fs::write("/tmp/../quota.txt", [1u8; 150]), Ok(()));
+    assert_eq!(quota.usage(), used);
+    fs::set_current_dir("/tmp")?;
+    assert_eq!(fs::write("../quota.txt", [2u8; 150]), Ok(()));
+    fs::set_current_dir("/")?;
+    assert_eq!(quota.usage(), used);
+    assert_eq!(fs::remove_file("tmp/../quota.txt"), Ok(()));
+    assert_eq!(quota.usage(), used);
+    quota.set_quota(None);
+
+    // /proc is mounted read-only
+    #[cfg(feature = "procfs")]
+    {
+        let fname = "/proc/sys/net/core/somaxconn";
+        assert_eq!(fs::read_to_string(fname)?, "4096\n");
+        assert_err!(fs::write(fname, "1\n"), PermissionDenied);
+        assert_err!(
+            OpenOptions::new().append(true).open(fname),
+            PermissionDenied
+        );
+        assert_err!(File::create("/proc/new-file"), PermissionDenied);
+        assert_err!(fs::create_dir("/proc/new-dir"), PermissionDenied);
+        assert_err!(fs::remove_file(fname), PermissionDenied);
+        assert_eq!(fs::read_to_string(fname)?, "4096\n");
+
+        // `..` climbs out of the read-only mount
+        assert_eq!(fs::write("/proc/../ro-escape.txt", "rw"), Ok(()));
+        fs::set_current_dir("/proc/sys")?;
+        assert_eq!(fs::write("../../ro-escape.txt", "rw\n"), Ok(()));
+        fs::set_current_dir("/")?;
+        assert_eq!(fs::read_to_string("/ro-escape.txt")?, "rw\n");
+        assert_eq!(fs::remove_file("/proc/sys/../../ro-escape.txt"), Ok(()));
+    }
+
     println!("test_devfs_ramfs() OK!");
     Ok(())
 }
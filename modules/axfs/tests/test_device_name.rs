@@ -0,0 +1,17 @@
+use std::collections::BTreeSet;
+
+#[test]
+fn test_device_name() {
+    let names: Vec<String> = (0..30).map(axfs::get_device_name).collect();
+    assert_eq!(names[0], "vda");
+    assert_eq!(names[1], "vdb");
+    assert_eq!(names[25], "vdz");
+    assert_eq!(names[26], "vdaa");
+    assert_eq!(names[27], "vdab");
+    assert_eq!(names[29], "vdad");
+    assert_eq!(axfs::get_device_name(26 + 26 * 26), "vdaaa");
+
+    // no two devices share a name, so none is overwritten at its mount point
+    let unique: BTreeSet<_> = names.iter().collect();
+    assert_eq!(unique.len(), names.len());
+}
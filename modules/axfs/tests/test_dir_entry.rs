@@ -0,0 +1,24 @@
+use axfs::fops::{DirEntry, DirEntryIter, FileType};
+
+#[test]
+fn test_dir_entry_iter() {
+    let long_name = "x".repeat(63);
+    let entries = [
+        DirEntry::new(".", FileType::Dir),
+        DirEntry::new("file.txt", FileType::File),
+        DirEntry::new("subdir", FileType::Dir),
+        DirEntry::new(&long_name, FileType::File),
+    ];
+
+    let items: Vec<_> = DirEntryIter::new(&entries).collect();
+    assert_eq!(
+        items,
+        [
+            (".", FileType::Dir),
+            ("file.txt", FileType::File),
+            ("subdir", FileType::Dir),
+            (long_name.as_str(), FileType::File),
+        ]
+    );
+    assert_eq!(DirEntryIter::new(&entries[..0]).count(), 0);
+}
@@ -0,0 +1,89 @@
+#![cfg(feature = "myfs")]
+
+use std::sync::Arc;
+
+use axdriver::AxDeviceContainer;
+use axdriver_block::ramdisk::RamDisk;
+use axerrno::AxError;
+use axfs::api as fs;
+use axfs::fops::{Disk, MyFileSystemIf};
+use axfs_ramfs::RamFileSystem;
+use axfs_vfs::VfsOps;
+
+struct MyFileSystemIfImpl;
+
+#[crate_interface::impl_interface]
+impl MyFileSystemIf for MyFileSystemIfImpl {
+    fn new_myfs(_disk: Disk) -> Arc<dyn VfsOps> {
+        Arc::new(RamFileSystem::new())
+    }
+}
+
+#[test]
+fn test_mount_at() {
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems(AxDeviceContainer::from_one(RamDisk::default())); // dummy disk, actually not used.
+
+    let fixture = Arc::new(RamFileSystem::new());
+    fixture
+        .root_dir()
+        .create("hello.txt", axfs_vfs::VfsNodeType::File)
+        .unwrap();
+    axfs::mount_at("/mnt", "ramfs", fixture).unwrap();
+    assert!(axfs::mount_at("/mnt", "ramfs", Arc::new(RamFileSystem::new())).is_err());
+    assert!(axfs::mount_at("/", "ramfs", Arc::new(RamFileSystem::new())).is_err());
+
+    assert!(fs::metadata("/mnt/hello.txt").unwrap().is_file());
+    fs::write("/mnt/hello.txt", "fixture\n").unwrap();
+    assert_eq!(fs::read_to_string("/mnt/hello.txt").unwrap(), "fixture\n");
+
+    fs::create_dir("/mnt/sub").unwrap();
+    fs::write("/mnt/sub/a.txt", "a").unwrap();
+    assert_eq!(fs::read_to_string("/mnt/sub/a.txt").unwrap(), "a");
+
+    // an existing directory can be used as a mount point
+    fs::create_dir("/fixtures").unwrap();
+    axfs::mount_at("/fixtures", "ramfs", Arc::new(RamFileSystem::new())).unwrap();
+    fs::write("/fixtures/b.txt", "b").unwrap();
+    assert_eq!(fs::read_to_string("/fixtures/b.txt").unwrap(), "b");
+
+    let mounts = axfs::list_mounts();
+    assert!(mounts.contains(&("/mnt".into(), "ramfs")));
+    assert!(mounts.contains(&("/fixtures".into(), "ramfs")));
+
+    // busy while another filesystem is mounted under it
+    fs::create_dir("/mnt/inner").unwrap();
+    axfs::mount_at("/mnt/inner", "ramfs", Arc::new(RamFileSystem::new())).unwrap();
+    assert!(axfs::unmount("/mnt").is_err());
+    axfs::unmount("/mnt/inner").unwrap();
+
+    // busy while it is the current directory
+    fs::set_current_dir("/fixtures").unwrap();
+    assert!(axfs::unmount("/fixtures").is_err());
+    fs::set_current_dir("/").unwrap();
+
+    // busy while a file under it is open
+    let file = fs::File::open("/fixtures/b.txt").unwrap();
+    assert_eq!(
+        axfs::unmount("/fixtures").unwrap_err(),
+        AxError::ResourceBusy
+    );
+    drop(file);
+
+    // a file reached through `..` is not on the mount and does not keep it busy
+    fs::write("/outside.txt", "outside").unwrap();
+    let file = fs::File::open("/fixtures/../outside.txt").unwrap();
+    axfs::unmount("/fixtures").unwrap();
+    drop(file);
+    assert_eq!(fs::read_to_string("/outside.txt").unwrap(), "outside");
+    assert!(fs::metadata("/fixtures/b.txt").is_err());
+    assert!(axfs::unmount("/fixtures").is_err());
+    assert!(
+        !axfs::list_mounts()
+            .iter()
+            .any(|(path, _)| path == "/fixtures")
+    );
+
+    // the mount point can be reused afterwards
+    axfs::mount_at("/fixtures", "ramfs", Arc::new(RamFileSystem::new())).unwrap();
+}
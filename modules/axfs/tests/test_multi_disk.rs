@@ -0,0 +1,39 @@
+#![cfg(not(feature = "myfs"))]
+
+use axdriver::AxDeviceContainer;
+use axdriver_block::ramdisk::RamDisk;
+use axfs::api as fs;
+
+const IMG_PATH: &str = "resources/fat16.img";
+
+fn make_disk() -> std::io::Result<RamDisk> {
+    let path = std::env::current_dir()?.join(IMG_PATH);
+    let data = std::fs::read(path)?;
+    Ok(RamDisk::from(&data))
+}
+
+#[test]
+fn test_multi_disk() {
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+
+    let root = make_disk().expect("failed to load disk image");
+    axfs::init_filesystems(AxDeviceContainer::from_one(root));
+
+    for name in ["vdb", "vdc"] {
+        let disk = make_disk().expect("failed to load disk image");
+        axfs::mount_block_device(&format!("/mnt/{}", name), disk).unwrap();
+    }
+    fs::write("/mnt/vdb/disk.txt", "vdb\n").unwrap();
+    fs::write("/mnt/vdc/disk.txt", "vdc\n").unwrap();
+    assert_eq!(fs::read_to_string("/mnt/vdb/disk.txt").unwrap(), "vdb\n");
+    assert_eq!(fs::read_to_string("/mnt/vdc/disk.txt").unwrap(), "vdc\n");
+    assert!(fs::metadata("/disk.txt").is_err());
+
+    let mounts = axfs::list_mounts();
+    assert!(mounts.contains(&("/mnt/vdb".into(), "fatfs")));
+    assert!(mounts.contains(&("/mnt/vdc".into(), "fatfs")));
+
+    // a disk without a FAT filesystem is rejected instead of panicking
+    assert!(axfs::mount_block_device("/mnt/vdd", RamDisk::new(0x10000)).is_err());
+    assert!(fs::metadata("/mnt/vdd/disk.txt").is_err());
+}
@@ -0,0 +1,34 @@
+#![cfg(not(feature = "myfs"))]
+
+use std::sync::Arc;
+
+use axdriver::AxDeviceContainer;
+use axdriver_block::ramdisk::RamDisk;
+use axfs::api as fs;
+use axfs_ramfs::RamFileSystem;
+
+#[test]
+fn test_register_fs_type() {
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+
+    axfs::register_fs_type(
+        "dummyfs",
+        Box::new(|_disk| Ok(Arc::new(RamFileSystem::new()) as _)),
+    )
+    .unwrap();
+    assert!(
+        axfs::register_fs_type(
+            "dummyfs",
+            Box::new(|_disk| Ok(Arc::new(RamFileSystem::new()) as _)),
+        )
+        .is_err()
+    );
+
+    // dummy disk, actually not used by the dummy filesystem
+    axfs::init_filesystems_as(AxDeviceContainer::from_one(RamDisk::default()), "dummyfs");
+
+    fs::write("/hello.txt", "dummy\n").unwrap();
+    assert_eq!(fs::read_to_string("/hello.txt").unwrap(), "dummy\n");
+    fs::create_dir("/dir").unwrap();
+    assert!(fs::metadata("/dir").unwrap().is_dir());
+}
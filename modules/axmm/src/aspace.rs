@@ -75,6 +75,39 @@ impl AddrSpace {
         Ok(())
     }
 
+    /// Creates a copy of the address space with its own memory image.
+    ///
+    /// Every area is mapped again in a new address space. Pages of allocation
+    /// mappings are copied to newly allocated frames, so writes to one address
+    /// space are not visible in the other. Pages of lazy mappings that have not
+    /// been touched yet stay unpopulated. Linear mappings refer to the same
+    /// physical memory in both address spaces.
+    ///
+    /// Page table entries added by [`copy_mappings_from`] do not belong to any
+    /// area and are not copied.
+    ///
+    /// [`copy_mappings_from`]: AddrSpace::copy_mappings_from
+    pub fn clone_mappings(&self) -> AxResult<Self> {
+        let mut new_aspace = Self::new_empty(self.base(), self.size())?;
+        for area in self.areas.iter() {
+            let backend = area.backend().clone();
+            let new_area =
+                MemoryArea::new(area.start(), area.size(), area.flags(), backend.clone());
+            new_aspace
+                .areas
+                .map(new_area, &mut new_aspace.pt, false)
+                .map_err(mapping_err_to_ax_err)?;
+            for vaddr in
+                PageIter4K::new(area.start(), area.end()).expect("Failed to create page iterator")
+            {
+                if !backend.clone_page(vaddr, &self.pt, &mut new_aspace.pt) {
+                    return Err(AxError::NoMemory);
+                }
+            }
+        }
+        Ok(new_aspace)
+    }
+
     /// Finds a free area that can accommodate the given size.
     ///
     /// The search starts from the given hint address, and the area should be within the given limit range.
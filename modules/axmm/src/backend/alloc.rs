@@ -85,6 +85,43 @@ impl Backend {
         true
     }
 
+    pub(crate) fn clone_page_alloc(
+        &self,
+        vaddr: VirtAddr,
+        src_pt: &PageTable,
+        dst_pt: &mut PageTable,
+        populate: bool,
+    ) -> bool {
+        let (src_frame, flags) = match src_pt.query(vaddr) {
+            Ok((frame, flags, _)) if !flags.is_empty() => (frame, flags),
+            _ => return true, // not populated yet, leave it to the page fault handler
+        };
+        let dst_frame = if populate {
+            // the frame was allocated when the area was mapped
+            match dst_pt.query(vaddr) {
+                Ok((frame, _, _)) => frame,
+                Err(_) => return false,
+            }
+        } else {
+            let Some(frame) = alloc_frame(false) else {
+                return false;
+            };
+            if dst_pt.remap(vaddr, frame, flags).map(|(_, tlb)| tlb.ignore()).is_err() {
+                dealloc_frame(frame);
+                return false;
+            }
+            frame
+        };
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                phys_to_virt(src_frame).as_ptr(),
+                phys_to_virt(dst_frame).as_mut_ptr(),
+                PAGE_SIZE_4K,
+            );
+        }
+        true
+    }
+
     pub(crate) fn handle_page_fault_alloc(
         &self,
         vaddr: VirtAddr,
@@ -71,6 +71,23 @@ impl MappingBackend for Backend {
 }
 
 impl Backend {
+    /// Copies the page at `vaddr` from `src_pt` to `dst_pt`, where this area
+    /// has already been mapped.
+    ///
+    /// Linear mappings refer to the same physical memory in both page tables,
+    /// so there is nothing to copy.
+    pub(crate) fn clone_page(
+        &self,
+        vaddr: VirtAddr,
+        src_pt: &PageTable,
+        dst_pt: &mut PageTable,
+    ) -> bool {
+        match *self {
+            Self::Linear { .. } => true,
+            Self::Alloc { populate } => self.clone_page_alloc(vaddr, src_pt, dst_pt, populate),
+        }
+    }
+
     pub(crate) fn handle_page_fault(
         &self,
         vaddr: VirtAddr,
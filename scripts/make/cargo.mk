@@ -51,5 +51,6 @@ endef
 define unit_test
   $(call run_cmd,cargo test,-p axfs $(1) $(verbose) -- --nocapture)
   $(call run_cmd,cargo test,-p axfs $(1) --features "myfs" $(verbose) -- --nocapture)
+  $(call run_cmd,cargo test,-p arceos_posix_api $(1) --features "fs pipe poll multitask" $(verbose) -- --nocapture)
   $(call run_cmd,cargo test,--workspace --exclude axfs $(1) $(verbose) -- --nocapture)
 endef
@@ -1,7 +1,7 @@
 use core::ffi::{c_char, c_int};
 
 use arceos_posix_api::{
-    sys_fstat, sys_getcwd, sys_lseek, sys_lstat, sys_open, sys_rename, sys_stat,
+    sys_fchdir, sys_fstat, sys_getcwd, sys_lseek, sys_lstat, sys_open, sys_rename, sys_stat,
 };
 
 use crate::{ctypes, utils::e};
@@ -57,6 +57,14 @@ pub unsafe extern "C" fn getcwd(buf: *mut c_char, size: usize) -> *mut c_char {
     sys_getcwd(buf, size)
 }
 
+/// Change the current directory to the directory referred to by `fd`.
+///
+/// Return 0 if success.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fchdir(fd: c_int) -> c_int {
+    e(sys_fchdir(fd))
+}
+
 /// Rename `old` to `new`
 /// If new exists, it is first removed.
 ///
@@ -2,6 +2,8 @@ use crate::{ctypes, utils::e};
 
 use core::ffi::c_int;
 
+#[cfg(feature = "poll")]
+use arceos_posix_api::sys_poll;
 #[cfg(feature = "select")]
 use arceos_posix_api::sys_select;
 #[cfg(feature = "epoll")]
@@ -52,3 +54,14 @@ pub unsafe extern "C" fn select(
 ) -> c_int {
     e(sys_select(nfds, readfds, writefds, exceptfds, timeout))
 }
+
+/// Wait for some event on a set of file descriptors.
+#[cfg(feature = "poll")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn poll(
+    fds: *mut ctypes::pollfd,
+    nfds: ctypes::nfds_t,
+    timeout: c_int,
+) -> c_int {
+    e(sys_poll(fds, nfds, timeout))
+}
@@ -19,10 +19,12 @@
 //!     - `fd`: Enable file descriptor table.
 //!     - `pipe`: Enable pipe support.
 //!     - `select`: Enable synchronous I/O multiplexing ([select]) support.
+//!     - `poll`: Enable synchronous I/O multiplexing ([poll]) support.
 //!     - `epoll`: Enable event polling ([epoll]) support.
 //!
 //! [ArceOS]: https://github.com/arceos-org/arceos
 //! [select]: https://man7.org/linux/man-pages/man2/select.2.html
+//! [poll]: https://man7.org/linux/man-pages/man2/poll.2.html
 //! [epoll]: https://man7.org/linux/man-pages/man7/epoll.7.html
 
 #![cfg_attr(all(not(test), not(doc)), no_std)]
@@ -52,7 +54,7 @@ mod utils;
 mod fd_ops;
 #[cfg(feature = "fs")]
 mod fs;
-#[cfg(any(feature = "select", feature = "epoll"))]
+#[cfg(any(feature = "select", feature = "poll", feature = "epoll"))]
 mod io_mpx;
 #[cfg(feature = "alloc")]
 mod malloc;
@@ -117,6 +119,8 @@ pub use self::pipe::pipe;
 
 #[cfg(feature = "select")]
 pub use self::io_mpx::select;
+#[cfg(feature = "poll")]
+pub use self::io_mpx::poll;
 #[cfg(feature = "epoll")]
 pub use self::io_mpx::{epoll_create, epoll_ctl, epoll_wait};
 